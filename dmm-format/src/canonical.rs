@@ -0,0 +1,61 @@
+//! Canonical textual form of maps.
+//!
+//! The canonical form is the TGM layout of [`DMM::canonicalize`]: one header comment, the
+//! dictionary sorted by key, keys given in the order of the tiles, datums ordered movables, turfs
+//! then areas, var edits sorted by name and indented with a tab.
+
+use dmm::DMM;
+
+use crate::de::from_str;
+use crate::error::Result;
+use crate::ser::to_string;
+
+/// Write the map in canonical form
+pub fn to_canonical_string(dmm: &DMM) -> String {
+    let mut dmm = dmm.clone();
+    dmm.canonicalize();
+    to_string(&dmm)
+}
+
+/// Whether `input` is a map already written in canonical form
+pub fn is_canonical(input: &str) -> Result<bool> {
+    Ok(to_canonical_string(&from_str(input)?) == input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical() {
+        let source = r#""aa" = (/area/bridge,/turf/open/floor{icon_state = "dark"; dir = 4},/obj/item)
+"ab" = (/obj/item,/turf/open/floor{dir = 4; icon_state = "dark"},/area/bridge)
+
+(1,1,1) = {"
+aaab
+"}
+"#;
+        assert!(!is_canonical(source).unwrap());
+        let canonical = to_canonical_string(&from_str(source).unwrap());
+        assert_eq!(
+            canonical,
+            r#"//MAP CONVERTED BY dmm2tgm.py THIS HEADER COMMENT PREVENTS RECONVERSION, DO NOT REMOVE
+"a" = (
+/obj/item,
+/turf/open/floor{
+	dir = 4;
+	icon_state = "dark"
+	},
+/area/bridge)
+
+(1,1,1) = {"
+a
+"}
+(2,1,1) = {"
+a
+"}
+"#
+        );
+        assert!(is_canonical(&canonical).unwrap());
+    }
+}
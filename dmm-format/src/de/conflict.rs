@@ -0,0 +1,148 @@
+//! Maps committed with git conflict markers.
+//!
+//! The text of each side is rebuilt by keeping the lines outside the conflict hunks and the lines
+//! of that side inside them, then parsed as a normal map.
+
+use dmm::merge::Merge;
+use dmm::DMM;
+
+use super::from_str;
+use crate::error::{Error, Result};
+
+/// Both sides of a conflicted map
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConflictedMap {
+    /// Common ancestor, only known when every hunk has a `|||||||` section (diff3 style)
+    pub base: Option<DMM>,
+    pub ours: DMM,
+    pub theirs: DMM,
+}
+
+impl ConflictedMap {
+    /// Merge both sides tile by tile, then resolve the remaining conflicts with [`Merge`].
+    ///
+    /// Without a base, every tile differing between the sides is a conflict.
+    pub fn merge(&self) -> Merge {
+        match &self.base {
+            Some(base) => dmm::merge(base, &self.ours, &self.theirs),
+            None => dmm::merge(&DMM::default(), &self.ours, &self.theirs),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Common,
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// Parse a map containing git conflict markers, in the dictionary or in the grid
+pub fn from_str_with_conflicts(input: &str) -> Result<ConflictedMap> {
+    let (mut ours, mut base, mut theirs) = (String::new(), String::new(), String::new());
+    let mut section = Section::Common;
+    let mut hunks = 0;
+    let mut hunks_with_base = 0;
+    for (index, line) in input.lines().enumerate() {
+        let invalid = || Error::InvalidConflictMarker(index + 1);
+        if line.starts_with("<<<<<<<") {
+            if section != Section::Common {
+                return Err(invalid());
+            }
+            hunks += 1;
+            section = Section::Ours;
+        } else if line.starts_with("|||||||") {
+            if section != Section::Ours {
+                return Err(invalid());
+            }
+            hunks_with_base += 1;
+            section = Section::Base;
+        } else if line.starts_with("=======") {
+            if section != Section::Ours && section != Section::Base {
+                return Err(invalid());
+            }
+            section = Section::Theirs;
+        } else if line.starts_with(">>>>>>>") {
+            if section != Section::Theirs {
+                return Err(invalid());
+            }
+            section = Section::Common;
+        } else {
+            let sides: &mut [&mut String] = match section {
+                Section::Common => &mut [&mut ours, &mut base, &mut theirs],
+                Section::Ours => &mut [&mut ours],
+                Section::Base => &mut [&mut base],
+                Section::Theirs => &mut [&mut theirs],
+            };
+            for side in sides {
+                side.push_str(line);
+                side.push('\n');
+            }
+        }
+    }
+    if section != Section::Common {
+        return Err(Error::InvalidConflictMarker(input.lines().count()));
+    }
+
+    Ok(ConflictedMap {
+        base: if hunks == hunks_with_base {
+            Some(from_str(&base)?)
+        } else {
+            None
+        },
+        ours: from_str(&ours)?,
+        theirs: from_str(&theirs)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str_with_conflicts() {
+        let source = r#""a" = (/turf/open/floor,/area/bridge)
+<<<<<<< HEAD
+"b" = (/turf/closed/wall,/area/bridge)
+||||||| base
+"b" = (/turf/closed/wall/r_wall,/area/bridge)
+=======
+"b" = (/turf/closed/wall/r_wall,/area/hallway)
+>>>>>>> feature
+
+(1,1,1) = {"
+<<<<<<< HEAD
+ab
+||||||| base
+aa
+=======
+ab
+ba
+>>>>>>> feature
+"}
+"#;
+        let conflicted = from_str_with_conflicts(source).unwrap();
+        assert_eq!(conflicted.ours.size(), (2, 1, 1));
+        assert_eq!(conflicted.theirs.size(), (2, 2, 1));
+        let base = conflicted.base.as_ref().unwrap();
+        assert_eq!(base.dictionary().len(), 2);
+
+        let merge = conflicted.merge();
+        // (2,1,1) changed on both sides, (1,2,1) only added by theirs
+        assert_eq!(merge.conflicts().len(), 1);
+        assert_eq!(merge.conflicts()[0].coords, (2, 1, 1));
+        assert_eq!(merge.merged().size(), (2, 2, 1));
+
+        let two_way = source.replace(
+            "||||||| base\n\"b\" = (/turf/closed/wall/r_wall,/area/bridge)\n",
+            "",
+        );
+        assert_eq!(from_str_with_conflicts(&two_way).unwrap().base, None);
+
+        assert!(matches!(
+            from_str_with_conflicts(&source.replace(">>>>>>> feature\n\n", "")),
+            Err(Error::InvalidConflictMarker(9))
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+mod conflict;
 mod parse;
 
 use std::io::Read;
@@ -6,6 +7,9 @@ use nom::types::CompleteStr;
 
 use self::parse::*;
 use crate::error::{Error, Result};
+use crate::ser::Layout;
+
+pub use self::conflict::{from_str_with_conflicts, ConflictedMap};
 
 pub fn from_reader<R: Read>(mut input: R) -> Result<::dmm::DMM> {
     let mut s = String::new();
@@ -14,14 +18,94 @@ pub fn from_reader<R: Read>(mut input: R) -> Result<::dmm::DMM> {
 }
 
 pub fn from_str(input: &str) -> Result<::dmm::DMM> {
-    parse_dmm(CompleteStr(input))
-        .map_err(|e| Error::Nom(e.into_error_kind()))
-        .and_then(|(remaining, dmm)| {
-            if !remaining.0.trim_end().is_empty() {
-                Err(Error::TrailingCharacters)
-            } else {
-                Ok(dmm)
-            }
+    match parse_dmm(CompleteStr(input)) {
+        Ok((remaining, dmm)) if remaining.0.trim_end().is_empty() => dmm.into_dmm(input),
+        Ok((remaining, _)) => {
+            let (line, column) = position(input, stop(remaining.0));
+            Err(Error::TrailingCharacters { line, column })
+        }
+        Err(e) => {
+            let (remaining, kind) = furthest(&e).unwrap_or((input, e.into_error_kind()));
+            let (line, column) = position(input, remaining);
+            Err(Error::Nom { kind, line, column })
+        }
+    }
+}
+
+/// Where parsing stops in `remaining`, the input left after the last complete entry
+fn stop(remaining: &str) -> &str {
+    let input = CompleteStr(remaining);
+    [
+        parse_dictionary_entry(input).err(),
+        parse_grid_entry(input).err(),
+    ]
+    .iter()
+    .flatten()
+    .filter_map(furthest)
+    .map(|(rest, _)| rest)
+    .min_by_key(|rest| rest.len())
+    .unwrap_or(remaining)
+}
+
+/// Furthest input reached by a failed parser, with the kind of its error
+fn furthest<'a>(error: &nom::Err<CompleteStr<'a>>) -> Option<(&'a str, nom::ErrorKind)> {
+    match error {
+        nom::Err::Error(context) | nom::Err::Failure(context) => nom::error_to_list(context)
+            .into_iter()
+            .min_by_key(|(rest, _)| rest.0.len())
+            .map(|(rest, kind)| (rest.0, kind)),
+        nom::Err::Incomplete(_) => None,
+    }
+}
+
+/// Line and column, starting at 1, where `remaining` starts in `input`
+fn position(input: &str, remaining: &str) -> (usize, usize) {
+    let parsed = &input[..input.len() - remaining.len()];
+    let line = parsed.matches('\n').count() + 1;
+    let column = parsed.len() - parsed.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Line and column, starting at 1, where `part`, a slice of `input`, starts
+fn position_of(input: &str, part: &str) -> (usize, usize) {
+    let offset = part.as_ptr() as usize - input.as_ptr() as usize;
+    position(input, &input[offset..])
+}
+
+/// Guess the layout of a map from the way its dictionary is written: TGM maps have one datum
+/// per line, classic ones write each entry on a single line.
+pub fn detect_layout(input: &str) -> Layout {
+    let first_entry = input.lines().find(|line| line.starts_with('"'));
+    match first_entry {
+        Some(line) if !line.trim_end().ends_with('(') => Layout::Classic,
+        _ => Layout::Tgm,
+    }
+}
+
+/// Number of characters of the keys of a map, read from its first dictionary entry
+pub fn detect_key_width(input: &str) -> Option<usize> {
+    let first_entry = input.lines().find(|line| line.starts_with('"'))?;
+    first_entry[1..].find('"')
+}
+
+/// Parse a var edit value written in DM syntax, such as `"thing"` or `list(1,2)`
+pub fn literal_from_str(input: &str) -> Result<::dmm::Literal> {
+    match parse_literal(CompleteStr(input.trim())) {
+        Ok((remaining, literal)) if remaining.0.is_empty() => Ok(literal.into()),
+        _ => Err(Error::InvalidLiteral(input.to_string())),
+    }
+}
+
+/// Parse UpdatePaths migration rules, one per line. Empty lines and lines starting with `#` are
+/// ignored.
+pub fn update_paths_from_str(input: &str) -> Result<Vec<::dmm::update_paths::Rule>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| match parse_rule(CompleteStr(line)) {
+            Ok((remaining, rule)) if remaining.0.trim_end().is_empty() => Ok(rule),
+            _ => Err(Error::InvalidRule(index + 1)),
         })
-        .map(std::convert::Into::into)
+        .collect()
 }
@@ -1,4 +1,4 @@
-use nom::{char, named, opt, sep, types::CompleteStr};
+use nom::{char, named, opt, types::CompleteStr};
 
 use super::literal::parse_path;
 use super::var_edit::parse_var_edit;
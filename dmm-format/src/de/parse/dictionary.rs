@@ -1,4 +1,4 @@
-use nom::{alpha, char, delimited, named, sep, types::CompleteStr};
+use nom::{alpha, char, delimited, named, types::CompleteStr};
 
 use super::{Datum, DictionaryEntry};
 
@@ -1,6 +1,4 @@
-use nom::{
-    alpha, char, digit, eat_separator, map, map_res, named, sep, tag, types::CompleteStr, value,
-};
+use nom::{alpha, char, digit, eat_separator, map, map_res, named, tag, types::CompleteStr, value};
 
 use super::dictionary::parse_dictionary_entry;
 use super::{DictionaryEntry, GridEntry, DMM};
@@ -17,7 +15,7 @@ named!(parse_grid<CompleteStr, Vec<GridEntry>>,
     ws_comm!(many0!(parse_grid_entry))
 );
 
-named!(parse_grid_entry<CompleteStr, GridEntry>,
+named!(pub parse_grid_entry<CompleteStr, GridEntry>,
     ws_comm!(
         do_parse!(
             coords: parse_grid_coords >>
@@ -45,14 +43,14 @@ named!(parse_grid_coords<CompleteStr, (u32, u32, u32)>,
     )
 );
 
-named!(parse_grid_line<CompleteStr, Vec<&str>>,
+named!(
+    parse_grid_line<CompleteStr<'_>, Vec<&str>>,
     ws_comm!(delimited!(tag!("{\""), parse_grid_keys, tag!("\"}")))
 );
 
-named!(parse_grid_keys<CompleteStr, Vec<&str>>,
-    ws_comm!(
-        many0!(map!(alpha, |c| c.0))
-    )
+named!(
+    parse_grid_keys<CompleteStr<'_>, Vec<&str>>,
+    ws_comm!(many0!(map!(alpha, |c| c.0)))
 );
 
 named!(eat_comma<CompleteStr, ()>,
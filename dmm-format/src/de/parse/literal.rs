@@ -11,12 +11,11 @@ use super::Literal;
 named!(pub parse_literal<CompleteStr, Literal>,
     alt!(
         tuple!(parse_number, not!(one_of!("Ee."))) => { |(i, _)| Literal::Number(i) }
-        | parse_float    => { |f| Literal::Float(f) }
-        | parse_string => { |s| Literal::Str(s) }
+        | parse_float    => { Literal::Float }
+        | parse_string => { Literal::Str }
         | parse_path   => { |p: CompleteStr| Literal::Path(p.0.to_string()) }
-        // TODO
-        | tag!("null") => { |_| Literal::Str("null".to_string()) }
-        | recognize!(parse_list)=> { |p: CompleteStr| Literal::Str(p.0.to_string()) }
+        | tag!("null") => { |_| Literal::Null }
+        | parse_list   => { Literal::List }
     )
 );
 
@@ -97,10 +96,7 @@ named!(pub parse_path<CompleteStr, CompleteStr>,
 );
 
 fn is_path_char(c: char) -> bool {
-    match c {
-        'a'..='z' | 'A'..='Z' | '0'..='9' | '/' | '_' => true,
-        _ => false,
-    }
+    matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '/' | '_')
 }
 
 named!(pub parse_list<CompleteStr, Vec<Literal>>,
@@ -168,4 +164,29 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_parse_null() {
+        assert_eq!(
+            parse_literal(CompleteStr("null")),
+            Ok((CompleteStr(""), Literal::Null))
+        );
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_literal(CompleteStr("list(1, \"a\", /obj/item, list(), null)")),
+            Ok((
+                CompleteStr(""),
+                Literal::List(vec![
+                    Literal::Number(1),
+                    Literal::Str("a".to_string()),
+                    Literal::Path("/obj/item".to_string()),
+                    Literal::List(Vec::new()),
+                    Literal::Null,
+                ])
+            ))
+        );
+    }
 }
@@ -1,5 +1,8 @@
 use std::convert::TryInto;
 
+use super::position_of;
+use crate::error::{Error, Result};
+
 use nom::{
     alt, char, many0, many1, map, named, none_of, one_of, opt, preceded, tag, tuple,
     types::CompleteStr, value,
@@ -51,11 +54,16 @@ mod datum;
 mod dictionary;
 mod dmm;
 mod literal;
+mod update_paths;
 mod var_edit;
 
-pub use self::dmm::parse_dmm;
+pub use self::dictionary::parse_dictionary_entry;
+pub use self::dmm::{parse_dmm, parse_grid_entry};
+pub use self::literal::parse_literal;
+pub use self::update_paths::parse_rule;
 
 /// Parsed DMM AST
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, PartialEq)]
 pub struct DMM<'s> {
     pub dictionary: Vec<DictionaryEntry<'s>>,
@@ -87,6 +95,8 @@ pub enum Literal {
     Str(String),
     Number(i64),
     Float(f64),
+    Null,
+    List(Vec<Literal>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -95,39 +105,76 @@ pub struct GridEntry<'s> {
     pub keys: Vec<&'s str>,
 }
 
-impl Into<::dmm::DMM> for DMM<'_> {
-    fn into(self) -> ::dmm::DMM {
-        ::dmm::DMM::new(
-            self.dictionary
-                .into_iter()
-                .map(|de| {
-                    (
-                        de.key.try_into().unwrap(),
-                        de.datums
-                            .into_iter()
-                            .map(std::convert::Into::into)
-                            .collect(),
-                    )
-                })
-                .collect(),
-            self.grid
-                .into_iter()
-                .map(|GridEntry { coords, keys }| {
-                    (
-                        coords,
-                        keys.into_iter().map(|k| k.try_into().unwrap()).collect(),
-                    )
-                })
-                .collect(),
-        )
+/// Keys of a column of the map, going down from its top tile
+type Column = ((u32, u32, u32), Vec<::dmm::Key>);
+
+impl DMM<'_> {
+    /// Convert to a map. `input` is the text that was parsed, used to locate errors.
+    pub fn into_dmm(self, input: &str) -> Result<::dmm::DMM> {
+        let key_width = self.dictionary.first().map_or(1, |de| de.key.len());
+        let dictionary = self
+            .dictionary
+            .into_iter()
+            .map(|de| {
+                let datums = de.datums.into_iter().map(Into::into).collect();
+                Ok((parse_key(de.key, input)?, datums))
+            })
+            .collect::<Result<_>>()?;
+        let mut grid = Vec::new();
+        for entry in self.grid {
+            grid.extend(entry.columns(key_width, input)?);
+        }
+        Ok(::dmm::DMM::new(dictionary, grid.into_iter().collect()))
+    }
+}
+
+/// Convert a key, `key` being a slice of `input`
+fn parse_key(key: &str, input: &str) -> Result<::dmm::Key> {
+    key.try_into().map_err(|()| {
+        let (line, column) = position_of(input, key);
+        Error::InvalidKey {
+            key: key.to_string(),
+            line,
+            column,
+        }
+    })
+}
+
+impl GridEntry<'_> {
+    /// Split the entry into columns of keys.
+    ///
+    /// Each line of the entry is a row of the map: a TGM entry is a single column with one key
+    /// per line, while a classic one has a whole row of keys on each line.
+    fn columns(self, key_width: usize, input: &str) -> Result<Vec<Column>> {
+        let (x, y, z) = self.coords;
+        let mut columns: Vec<Column> = Vec::new();
+        for row in self.keys {
+            let invalid_row = || {
+                let (line, column) = position_of(input, row);
+                Error::InvalidGridRow { line, column }
+            };
+            if row.len() % key_width != 0 {
+                return Err(invalid_row());
+            }
+            for (i, key) in row.as_bytes().chunks_exact(key_width).enumerate() {
+                let key = std::str::from_utf8(key).map_err(|_| invalid_row())?;
+                let key = parse_key(key, input)?;
+                if columns.len() <= i {
+                    columns.push(((x + i as u32, y, z), Vec::new()));
+                }
+                columns[i].1.push(key);
+            }
+        }
+        Ok(columns)
     }
 }
 
-impl Into<::dmm::Datum> for Datum<'_> {
-    fn into(self) -> ::dmm::Datum {
+impl From<Datum<'_>> for ::dmm::Datum {
+    fn from(datum: Datum<'_>) -> Self {
         ::dmm::Datum::with_var_edits(
-            self.path,
-            self.var_edits
+            datum.path,
+            datum
+                .var_edits
                 .into_iter()
                 .map(|VarEdit { identifier, value }| (identifier.to_string(), value.into()))
                 .collect(),
@@ -135,13 +182,15 @@ impl Into<::dmm::Datum> for Datum<'_> {
     }
 }
 
-impl Into<::dmm::Literal> for Literal {
-    fn into(self) -> ::dmm::Literal {
-        match self {
+impl From<Literal> for ::dmm::Literal {
+    fn from(literal: Literal) -> Self {
+        match literal {
             Literal::Float(f) => ::dmm::Literal::Float(f),
             Literal::Number(n) => ::dmm::Literal::Number(n),
             Literal::Path(p) => ::dmm::Literal::Path(p),
             Literal::Str(s) => ::dmm::Literal::Str(s),
+            Literal::Null => ::dmm::Literal::Null,
+            Literal::List(l) => ::dmm::Literal::List(l.into_iter().map(Into::into).collect()),
         }
     }
 }
@@ -0,0 +1,185 @@
+use dmm::update_paths::{Matcher, Replacement, Rule, VarAction, VarFilter};
+use nom::{
+    char, delimited, do_parse, named, opt, separated_list, separated_nonempty_list, tag,
+    terminated, types::CompleteStr,
+};
+
+use super::literal::{parse_literal, parse_path};
+use super::var_edit::parse_identifier;
+use super::Literal;
+
+named!(pub parse_rule<CompleteStr, Rule>,
+    ws_comm!(
+        do_parse!(
+            matcher: parse_matcher >>
+            char!(':') >>
+            replacements: alt!(
+                tag!("@DELETE") => { |_| Vec::new() }
+                | separated_nonempty_list!(char!(','), parse_replacement)
+            ) >>
+            (Rule { matcher, replacements })
+        )
+    )
+);
+
+named!(parse_matcher<CompleteStr, Matcher>,
+    ws_comm!(
+        do_parse!(
+            path: parse_type_path >>
+            filters: opt!(
+                delimited!(
+                    char!('{'),
+                    terminated!(separated_list!(char!(';'), parse_filter), opt!(char!(';'))),
+                    char!('}')
+                )
+            ) >>
+            (Matcher { path: path.0, subtypes: path.1, filters: filters.unwrap_or_default() })
+        )
+    )
+);
+
+named!(parse_filter<CompleteStr, (String, VarFilter)>,
+    ws_comm!(
+        do_parse!(
+            var: parse_identifier >>
+            char!('=') >>
+            filter: alt!(
+                tag!("@UNSET") => { |_| VarFilter::Unset }
+                | parse_literal => { |l: Literal| VarFilter::Equals(l.into()) }
+            ) >>
+            ((var.0.to_string(), filter))
+        )
+    )
+);
+
+named!(parse_replacement<CompleteStr, Replacement>,
+    ws_comm!(
+        do_parse!(
+            path: parse_type_path >>
+            vars: opt!(
+                delimited!(
+                    char!('{'),
+                    terminated!(
+                        separated_list!(char!(';'), parse_replacement_var),
+                        opt!(char!(';'))
+                    ),
+                    char!('}')
+                )
+            ) >>
+            ({
+                let vars = vars.unwrap_or_default();
+                Replacement {
+                    path: path.0,
+                    subtypes: path.1,
+                    keep_old: vars.iter().any(Option::is_none),
+                    vars: vars.into_iter().flatten().collect(),
+                }
+            })
+        )
+    )
+);
+
+// A var of a replacement, or `None` for `@OLD`
+named!(parse_replacement_var<CompleteStr, Option<(String, VarAction)>>,
+    ws_comm!(
+        alt!(
+            do_parse!(
+                var: parse_identifier >>
+                char!('=') >>
+                action: alt!(
+                    tag!("@SKIP") => { |_| VarAction::Skip }
+                    | preceded!(tag!("@OLD:"), parse_identifier) => {
+                        |v: CompleteStr| VarAction::Old(v.0.to_string())
+                    }
+                    | tag!("@OLD") => { |_| VarAction::Old(var.0.to_string()) }
+                    | parse_literal => { |l: Literal| VarAction::Set(l.into()) }
+                ) >>
+                (Some((var.0.to_string(), action)))
+            )
+            | tag!("@OLD") => { |_| None }
+        )
+    )
+);
+
+// A path, with whether it ends with `/@SUBTYPES`
+named!(parse_type_path<CompleteStr, (String, bool)>,
+    do_parse!(
+        path: parse_path >>
+        subtypes: opt!(tag!("@SUBTYPES")) >>
+        (match subtypes {
+            Some(_) => (path.0.trim_end_matches('/').to_string(), true),
+            None => (path.0.to_string(), false),
+        })
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            parse_rule(CompleteStr(
+                "/obj/old/@SUBTYPES{dir=4; name=@UNSET} : /obj/new/@SUBTYPES{@OLD; dir=@SKIP; \
+                 desc=@OLD:name; id=@OLD; icon_state=\"on\"}, /obj/other"
+            )),
+            Ok((
+                CompleteStr(""),
+                Rule {
+                    matcher: Matcher {
+                        path: "/obj/old".to_string(),
+                        subtypes: true,
+                        filters: vec![
+                            (
+                                "dir".to_string(),
+                                VarFilter::Equals(dmm::Literal::Number(4))
+                            ),
+                            ("name".to_string(), VarFilter::Unset),
+                        ],
+                    },
+                    replacements: vec![
+                        Replacement {
+                            path: "/obj/new".to_string(),
+                            subtypes: true,
+                            keep_old: true,
+                            vars: vec![
+                                ("dir".to_string(), VarAction::Skip),
+                                ("desc".to_string(), VarAction::Old("name".to_string())),
+                                ("id".to_string(), VarAction::Old("id".to_string())),
+                                (
+                                    "icon_state".to_string(),
+                                    VarAction::Set(dmm::Literal::Str("on".to_string()))
+                                ),
+                            ],
+                        },
+                        Replacement {
+                            path: "/obj/other".to_string(),
+                            subtypes: false,
+                            keep_old: false,
+                            vars: Vec::new(),
+                        },
+                    ],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_delete_rule() {
+        assert_eq!(
+            parse_rule(CompleteStr("/obj/thing : @DELETE")),
+            Ok((
+                CompleteStr(""),
+                Rule {
+                    matcher: Matcher {
+                        path: "/obj/thing".to_string(),
+                        subtypes: false,
+                        filters: Vec::new(),
+                    },
+                    replacements: Vec::new(),
+                }
+            ))
+        );
+    }
+}
@@ -1,6 +1,5 @@
 use nom::{
-    alpha, alphanumeric, alt, char, many0, named, opt, recognize, sep, tag, tuple,
-    types::CompleteStr,
+    alpha, alphanumeric, alt, char, many0, named, opt, recognize, tag, tuple, types::CompleteStr,
 };
 
 use super::literal::parse_literal;
@@ -17,7 +16,7 @@ named!(pub parse_var_edit<CompleteStr, VarEdit>,
     )
 );
 
-named!(parse_identifier<CompleteStr, CompleteStr>,
+named!(pub parse_identifier<CompleteStr, CompleteStr>,
     recognize!(
         tuple!(
             alt!(alpha | tag!("_")),
@@ -1,4 +1,3 @@
-use std;
 use std::fmt::{self, Display};
 use std::io;
 
@@ -7,25 +6,80 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     Custom(String),
-    /// Trailing character after the data
-    TrailingCharacters,
-    Nom(nom::ErrorKind),
+    /// Characters that could not be parsed, at the given line and column
+    TrailingCharacters {
+        line: usize,
+        column: usize,
+    },
+    /// The map could not be parsed, at the given line and column
+    Nom {
+        kind: nom::ErrorKind,
+        line: usize,
+        column: usize,
+    },
     Io(io::Error),
+    /// Invalid UpdatePaths rule at the given line
+    InvalidRule(usize),
+    /// Dictionary or grid key that is not a valid key, at the given line and column
+    InvalidKey {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+    /// Grid row whose length is not a multiple of the key width, at the given line and column
+    InvalidGridRow {
+        line: usize,
+        column: usize,
+    },
+    /// The classic layout cannot write a map without this tile
+    MissingTile(u32, u32, u32),
+    /// Unexpected or unterminated conflict marker at the given line
+    InvalidConflictMarker(usize),
+    /// Invalid var edit value
+    InvalidLiteral(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(std::error::Error::description(self))
-    }
-}
-
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
         match *self {
-            Error::Custom(ref msg) => msg,
-            Error::TrailingCharacters => "unexpected trailing characters after the data",
-            Error::Nom(ref e) => e.description(),
-            Error::Io(ref e) => e.description(),
+            Error::Custom(ref msg) => f.write_str(msg),
+            Error::TrailingCharacters { line, column } => write!(
+                f,
+                "unexpected characters at line {}, column {}",
+                line, column
+            ),
+            Error::Nom {
+                ref kind,
+                line,
+                column,
+            } => write!(
+                f,
+                "{} at line {}, column {}",
+                kind.description(),
+                line,
+                column
+            ),
+            Error::Io(ref e) => e.fmt(f),
+            Error::InvalidRule(line) => write!(f, "invalid UpdatePaths rule at line {}", line),
+            Error::InvalidKey {
+                ref key,
+                line,
+                column,
+            } => write!(
+                f,
+                "invalid key \"{}\" at line {}, column {}",
+                key, line, column
+            ),
+            Error::InvalidGridRow { line, column } => {
+                write!(f, "invalid grid row at line {}, column {}", line, column)
+            }
+            Error::MissingTile(x, y, z) => write!(f, "missing tile ({},{},{})", x, y, z),
+            Error::InvalidConflictMarker(line) => {
+                write!(f, "invalid conflict marker at line {}", line)
+            }
+            Error::InvalidLiteral(ref value) => write!(f, "invalid value {}", value),
         }
     }
 }
+
+impl std::error::Error for Error {}
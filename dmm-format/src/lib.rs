@@ -1,6 +1,15 @@
+mod canonical;
 mod de;
 mod error;
 mod ser;
 
-pub use de::{from_reader, from_str};
+pub use canonical::{is_canonical, to_canonical_string};
+pub use de::{
+    detect_key_width, detect_layout, from_reader, from_str, from_str_with_conflicts,
+    literal_from_str, update_paths_from_str, ConflictedMap,
+};
 pub use error::{Error, Result};
+pub use ser::{
+    datum_to_string, literal_to_string, rule_to_string, to_string, to_string_with_reference,
+    to_writer, to_writer_with_reference, type_definition_to_string, Layout, Serializer,
+};
@@ -1 +1,444 @@
+use std::collections::HashMap;
+use std::io::Write;
 
+use dmm::update_paths::{Rule, VarAction, VarFilter};
+use dmm::{Datum, Key, Literal, DMM};
+
+use crate::error::{Error, Result};
+
+const TGM_HEADER: &str =
+    "//MAP CONVERTED BY dmm2tgm.py THIS HEADER COMMENT PREVENTS RECONVERSION, DO NOT REMOVE";
+
+/// How the grid of a map is written
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// One column of the map per grid entry, one key and one datum per line, as written by
+    /// tgstation's mapmerge
+    Tgm,
+    /// One z-level per grid entry and one row per line, as written by Dream Maker
+    Classic,
+}
+
+/// Map writer options
+#[derive(Clone, Copy, Debug)]
+pub struct Serializer<'r> {
+    layout: Layout,
+    reference: Option<&'r DMM>,
+    key_width: usize,
+}
+
+impl Default for Serializer<'_> {
+    fn default() -> Self {
+        Serializer {
+            layout: Layout::Tgm,
+            reference: None,
+            key_width: 1,
+        }
+    }
+}
+
+impl<'r> Serializer<'r> {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Reuse the keys of `reference`, usually the map being written was read from, for the
+    /// dictionary entries that did not change, so only the changed entries and grid lines differ
+    /// from it.
+    ///
+    /// The keys are at least as wide as the largest key of `reference` needs, which can be less
+    /// than the width of its file: pass that width, from
+    /// [`detect_key_width`](crate::detect_key_width), to [`Serializer::key_width`] to keep it.
+    pub fn reference(mut self, reference: &'r DMM) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Write the keys with at least `key_width` characters, such as the width of the file the
+    /// map was read from. Wider keys are used when the map needs them.
+    pub fn key_width(mut self, key_width: usize) -> Self {
+        self.key_width = key_width;
+        self
+    }
+
+    pub fn to_writer<W: Write>(&self, mut writer: W, dmm: &DMM) -> Result<()> {
+        let mut dmm = dmm.clone();
+        let mut key_width = self.key_width;
+        if let Some(reference) = self.reference {
+            dmm.preserve_keys(reference.dictionary());
+            key_width = key_width.max(self::key_width(reference));
+        }
+        key_width = key_width.max(self::key_width(&dmm));
+
+        match self.layout {
+            Layout::Tgm => write_tgm(&mut writer, &dmm, key_width).map_err(Error::Io),
+            Layout::Classic => write_classic(&mut writer, &dmm, key_width),
+        }
+    }
+
+    pub fn to_string(&self, dmm: &DMM) -> Result<String> {
+        let mut output = Vec::new();
+        self.to_writer(&mut output, dmm)?;
+        Ok(String::from_utf8(output).expect("the map is written as UTF-8"))
+    }
+}
+
+/// Write the map in the TGM layout
+pub fn to_writer<W: Write>(writer: W, dmm: &DMM) -> Result<()> {
+    Serializer::new().to_writer(writer, dmm)
+}
+
+pub fn to_string(dmm: &DMM) -> String {
+    Serializer::new()
+        .to_string(dmm)
+        .expect("the TGM layout can write any map")
+}
+
+/// Write the map like [`to_writer`], reusing the keys of `reference`
+/// (see [`Serializer::reference`])
+pub fn to_writer_with_reference<W: Write>(writer: W, dmm: &DMM, reference: &DMM) -> Result<()> {
+    Serializer::new()
+        .reference(reference)
+        .to_writer(writer, dmm)
+}
+
+pub fn to_string_with_reference(dmm: &DMM, reference: &DMM) -> String {
+    Serializer::new()
+        .reference(reference)
+        .to_string(dmm)
+        .expect("the TGM layout can write any map")
+}
+
+/// Write a datum in DM syntax on a single line, such as `/obj/item{name = "thing"}`
+pub fn datum_to_string(datum: &Datum) -> String {
+    let mut output = Vec::new();
+    write_datum_inline(&mut output, datum).expect("writing to a Vec cannot fail");
+    String::from_utf8(output).expect("the datum is written as UTF-8")
+}
+
+/// Write a var edit value in DM syntax
+pub fn literal_to_string(literal: &Literal) -> String {
+    let mut output = Vec::new();
+    write_literal(&mut output, literal).expect("writing to a Vec cannot fail");
+    String::from_utf8(output).expect("the literal is written as UTF-8")
+}
+
+/// Write a datum as the DM definition of its type, its var edits becoming var definitions
+pub fn type_definition_to_string(datum: &Datum) -> String {
+    let mut definition = datum.path().to_string();
+    for (var, value) in sorted_var_edits(datum) {
+        definition.push_str(&format!("\n\t{} = {}", var, literal_to_string(value)));
+    }
+    definition
+}
+
+/// Write an UpdatePaths rule, as parsed by [`update_paths_from_str`](crate::update_paths_from_str)
+pub fn rule_to_string(rule: &Rule) -> String {
+    let type_path = |path: &str, subtypes| {
+        if subtypes {
+            format!("{}/@SUBTYPES", path)
+        } else {
+            path.to_string()
+        }
+    };
+    let braced = |items: Vec<String>| {
+        if items.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", items.join("; "))
+        }
+    };
+
+    let matcher = &rule.matcher;
+    let filters = matcher
+        .filters
+        .iter()
+        .map(|(var, filter)| match filter {
+            VarFilter::Equals(value) => format!("{} = {}", var, literal_to_string(value)),
+            VarFilter::Unset => format!("{} = @UNSET", var),
+        })
+        .collect();
+    let replacements: Vec<_> = rule
+        .replacements
+        .iter()
+        .map(|replacement| {
+            let old = if replacement.keep_old {
+                Some("@OLD".to_string())
+            } else {
+                None
+            };
+            let vars = old
+                .into_iter()
+                .chain(replacement.vars.iter().map(|(var, action)| match action {
+                    VarAction::Set(value) => format!("{} = {}", var, literal_to_string(value)),
+                    VarAction::Old(old) if old == var => format!("{} = @OLD", var),
+                    VarAction::Old(old) => format!("{} = @OLD:{}", var, old),
+                    VarAction::Skip => format!("{} = @SKIP", var),
+                }))
+                .collect();
+            format!(
+                "{}{}",
+                type_path(&replacement.path, replacement.subtypes),
+                braced(vars)
+            )
+        })
+        .collect();
+    format!(
+        "{}{} : {}",
+        type_path(&matcher.path, matcher.subtypes),
+        braced(filters),
+        if replacements.is_empty() {
+            "@DELETE".to_string()
+        } else {
+            replacements.join(", ")
+        }
+    )
+}
+
+/// Number of characters needed to write every key of the map
+fn key_width(dmm: &DMM) -> usize {
+    dmm.dictionary()
+        .keys()
+        .chain(dmm.grid().values().flatten())
+        .map(|key| key.width())
+        .max()
+        .unwrap_or(1)
+}
+
+fn write_tgm<W: Write>(writer: &mut W, dmm: &DMM, key_width: usize) -> std::io::Result<()> {
+    writeln!(writer, "{}", TGM_HEADER)?;
+
+    let mut keys: Vec<&Key> = dmm.dictionary().keys().collect();
+    keys.sort_unstable();
+    for key in keys {
+        write!(writer, "\"{}\" = (", key.to_str_with_width(key_width))?;
+        let datums = &dmm.dictionary()[key];
+        for (i, datum) in datums.iter().enumerate() {
+            writer.write_all(if i == 0 { b"\n" } else { b",\n" })?;
+            write_datum(writer, datum)?;
+        }
+        if datums.is_empty() {
+            writeln!(writer)?;
+        }
+        writeln!(writer, ")")?;
+    }
+    writeln!(writer)?;
+
+    let mut columns: Vec<_> = dmm.grid().iter().collect();
+    columns.sort_unstable_by_key(|(&(x, y, z), _)| (z, y, x));
+    let mut previous_z = columns.first().map(|((_, _, z), _)| *z);
+    for (&(x, y, z), keys) in columns {
+        // Z-levels are separated by an empty line
+        if previous_z != Some(z) {
+            writeln!(writer)?;
+            previous_z = Some(z);
+        }
+        writeln!(writer, "({},{},{}) = {{\"", x, y, z)?;
+        for key in keys {
+            writeln!(writer, "{}", key.to_str_with_width(key_width))?;
+        }
+        writeln!(writer, "\"}}")?;
+    }
+    Ok(())
+}
+
+/// Write the map with every z-level as a block of rows. Every tile of the map must exist.
+fn write_classic<W: Write>(writer: &mut W, dmm: &DMM, key_width: usize) -> Result<()> {
+    let mut keys: Vec<&Key> = dmm.dictionary().keys().collect();
+    keys.sort_unstable();
+    for key in keys {
+        write!(writer, "\"{}\" = (", key.to_str_with_width(key_width)).map_err(Error::Io)?;
+        for (i, datum) in dmm.dictionary()[key].iter().enumerate() {
+            if i != 0 {
+                writer.write_all(b",").map_err(Error::Io)?;
+            }
+            write_datum_inline(writer, datum).map_err(Error::Io)?;
+        }
+        writeln!(writer, ")").map_err(Error::Io)?;
+    }
+
+    let mut tiles = HashMap::new();
+    for (&(x, y, z), keys) in dmm.grid() {
+        for (i, key) in keys.iter().enumerate() {
+            tiles.insert((x, y + i as u32, z), *key);
+        }
+    }
+    let (width, height, depth) = dmm.size();
+    for z in 1..=depth {
+        let mut block = format!("\n(1,1,{}) = {{\"\n", z);
+        for y in 1..=height {
+            for x in 1..=width {
+                let key = tiles.get(&(x, y, z)).ok_or(Error::MissingTile(x, y, z))?;
+                block.push_str(&key.to_str_with_width(key_width));
+            }
+            block.push('\n');
+        }
+        block.push_str("\"}\n");
+        writer.write_all(block.as_bytes()).map_err(Error::Io)?;
+    }
+    Ok(())
+}
+
+fn write_datum<W: Write>(writer: &mut W, datum: &Datum) -> std::io::Result<()> {
+    writer.write_all(datum.path().as_bytes())?;
+    if datum.var_edits().is_empty() {
+        return Ok(());
+    }
+
+    writer.write_all(b"{")?;
+    for (i, (var, value)) in sorted_var_edits(datum).into_iter().enumerate() {
+        writer.write_all(if i == 0 { b"\n\t" } else { b";\n\t" })?;
+        write!(writer, "{} = ", var)?;
+        write_literal(writer, value)?;
+    }
+    writer.write_all(b"\n\t}")
+}
+
+/// Write a datum on a single line, as in the classic layout
+fn write_datum_inline<W: Write>(writer: &mut W, datum: &Datum) -> std::io::Result<()> {
+    writer.write_all(datum.path().as_bytes())?;
+    if datum.var_edits().is_empty() {
+        return Ok(());
+    }
+
+    writer.write_all(b"{")?;
+    for (i, (var, value)) in sorted_var_edits(datum).into_iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b"; ")?;
+        }
+        write!(writer, "{} = ", var)?;
+        write_literal(writer, value)?;
+    }
+    writer.write_all(b"}")
+}
+
+fn sorted_var_edits(datum: &Datum) -> Vec<(&String, &Literal)> {
+    let mut vars: Vec<_> = datum.var_edits().iter().collect();
+    vars.sort_unstable_by_key(|(var, _)| *var);
+    vars
+}
+
+fn write_literal<W: Write>(writer: &mut W, literal: &Literal) -> std::io::Result<()> {
+    match literal {
+        Literal::Path(p) => writer.write_all(p.as_bytes()),
+        Literal::Str(s) => write_string(writer, s),
+        Literal::Number(n) => write!(writer, "{}", n),
+        Literal::Float(f) => write_float(writer, *f),
+        Literal::Null => writer.write_all(b"null"),
+        Literal::List(list) => {
+            writer.write_all(b"list(")?;
+            for (i, item) in list.iter().enumerate() {
+                if i != 0 {
+                    writer.write_all(b",")?;
+                }
+                write_literal(writer, item)?;
+            }
+            writer.write_all(b")")
+        }
+    }
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> std::io::Result<()> {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            // `\i` (as in `\improper`) is kept as is by the parser
+            '\\' if chars.peek() == Some(&'i') => escaped.push('\\'),
+            '\\' => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    writer.write_all(escaped.as_bytes())
+}
+
+/// Write a float like BYOND does, with an exponent for large and small numbers (`5e+006`)
+fn write_float<W: Write>(writer: &mut W, f: f64) -> std::io::Result<()> {
+    if f != 0.0 && (f.abs() >= 1e6 || f.abs() < 1e-4) {
+        let formatted = format!("{:e}", f);
+        let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+        let exponent: i32 = exponent[1..].parse().unwrap();
+        let sign = if exponent < 0 { '-' } else { '+' };
+        write!(writer, "{}e{}{:03}", mantissa, sign, exponent.abs())
+    } else {
+        write!(writer, "{}", f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_literal() {
+        assert_eq!(literal_to_string(&Literal::Float(5e6)), "5e+006");
+        assert_eq!(literal_to_string(&Literal::Float(-1.5e-5)), "-1.5e-005");
+        assert_eq!(literal_to_string(&Literal::Float(0.5)), "0.5");
+        assert_eq!(
+            literal_to_string(&Literal::Str("\\improper \"Bob\"\\\n".to_string())),
+            "\"\\improper \\\"Bob\\\"\\\\\\n\""
+        );
+        assert_eq!(
+            literal_to_string(&Literal::List(vec![
+                Literal::Number(1),
+                Literal::Null,
+                Literal::Path("/obj".to_string())
+            ])),
+            "list(1,null,/obj)"
+        );
+    }
+
+    #[test]
+    fn test_write_rule() {
+        let source = "/obj/old/@SUBTYPES{dir = 4; name = @UNSET} : /obj/new/@SUBTYPES{@OLD; \
+                      dir = @SKIP; desc = @OLD:name; id = @OLD; icon_state = \"on\"}, /obj/other";
+        let rules = crate::update_paths_from_str(source).unwrap();
+        assert_eq!(rule_to_string(&rules[0]), source);
+        assert_eq!(
+            rule_to_string(&crate::update_paths_from_str("/obj/thing : @DELETE").unwrap()[0]),
+            "/obj/thing : @DELETE"
+        );
+
+        let definition = Datum::with_var_edits(
+            "/obj/structure/sign/directional",
+            vec![
+                ("pixel_y".to_string(), Literal::Number(32)),
+                ("dir".to_string(), Literal::Number(1)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            type_definition_to_string(&definition),
+            "/obj/structure/sign/directional\n\tdir = 1\n\tpixel_y = 32"
+        );
+    }
+
+    #[test]
+    fn test_write_datum() {
+        let datum = Datum::with_var_edits(
+            "/obj/machinery/airalarm",
+            vec![
+                ("pixel_y".to_string(), Literal::Number(23)),
+                ("frequency".to_string(), Literal::Number(1439)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut output = Vec::new();
+        write_datum(&mut output, &datum).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "/obj/machinery/airalarm{\n\tfrequency = 1439;\n\tpixel_y = 23\n\t}"
+        );
+    }
+}
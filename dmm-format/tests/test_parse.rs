@@ -59,3 +59,187 @@ fn test_parse_reader() {
     let file = std::fs::File::open("tests/multiz.dmm").expect("file should be here");
     assert!(dbg!(dmm_format::from_reader(file)).is_ok());
 }
+
+#[test]
+fn test_update_paths_from_str() {
+    let rules = dmm_format::update_paths_from_str(
+        "# Repath the fire alarms\n\n/obj/machinery/firealarm : /obj/machinery/fire_alarm{@OLD; name=@SKIP}\n",
+    )
+    .expect("Should have parsed");
+    let mut dmm = dmm_format::from_str(
+        r#""aa" = (/obj/machinery/firealarm{dir = 8; name = "thing"}, /turf/open/floor)
+(1,1,1) = {"aa"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(dmm.update_paths(&rules), vec![1]);
+    assert_eq!(
+        dmm.iter().next().unwrap().1[0],
+        Datum::with_var_edits(
+            "/obj/machinery/fire_alarm",
+            vec![("dir".to_string(), dmm::Literal::Number(8))]
+                .into_iter()
+                .collect()
+        )
+    );
+
+    assert!(matches!(
+        dmm_format::update_paths_from_str("/obj/a : /obj/b\n/obj/c :"),
+        Err(dmm_format::Error::InvalidRule(2))
+    ));
+}
+
+#[test]
+fn test_round_trip() {
+    let source = std::fs::read_to_string("tests/multiz.dmm").expect("file should be here");
+    let dmm = dmm_format::from_str(&source).expect("Should have parsed");
+
+    assert_eq!(dmm_format::to_string(&dmm), source);
+}
+
+#[test]
+fn test_preserve_keys() {
+    let source = std::fs::read_to_string("tests/multiz.dmm").expect("file should be here");
+    let original = dmm_format::from_str(&source).expect("Should have parsed");
+
+    // Shuffle the keys, then edit one tile
+    let mut edited = dmm::DMM::join_z_levels(original.split_z_levels().into_iter().rev());
+    edited.reorder_z_levels(&[3, 2, 1]).unwrap();
+    edited.set_tile((1, 1, 1), &[Datum::new("/turf/open/space/edited")]);
+    edited.prune_dictionary();
+
+    let output = dmm_format::to_string_with_reference(&edited, &original);
+    let mut lines = HashMap::new();
+    for line in source.lines() {
+        *lines.entry(line).or_insert(0) += 1;
+    }
+    let added: Vec<_> = output
+        .lines()
+        .filter(|line| match lines.get_mut(line) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    // A new dictionary entry in a free slot, and the edited grid line
+    assert_eq!(added, vec!["\"aF\" = (", "/turf/open/space/edited)", "aF"]);
+    assert_eq!(output.lines().count(), source.lines().count() + 2);
+}
+
+#[test]
+fn test_key_width() {
+    let source = r#""aa" = (/turf/open/floor,/area/bridge)
+"ab" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+abaa
+"}
+"#;
+    assert_eq!(dmm_format::detect_key_width(source), Some(2));
+    let dmm = dmm_format::from_str(source).expect("Should have parsed");
+
+    // Two entries only need one character, unless the width of the file is kept
+    let output = dmm_format::to_string(&dmm);
+    assert!(output.contains("\n\"b\" = (\n"));
+    let output = dmm_format::Serializer::new()
+        .layout(dmm_format::Layout::Classic)
+        .key_width(2)
+        .to_string(&dmm)
+        .unwrap();
+    assert_eq!(output, source);
+}
+
+#[test]
+fn test_ragged_grid() {
+    let source = r#""aa" = (/turf/open/floor,/area/bridge)
+"ab" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+abaa
+aba
+"}
+"#;
+    let error = dmm_format::from_str(source).unwrap_err();
+    assert!(matches!(
+        error,
+        dmm_format::Error::InvalidGridRow { line: 6, column: 1 }
+    ));
+}
+
+#[test]
+fn test_classic_layout() {
+    let source = r#""a" = (/turf/open/floor{icon_state = "dark"; dir = 4},/area/bridge)
+"b" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+bbb
+bab
+"}
+
+(1,1,2) = {"
+aab
+aab
+"}
+"#;
+    assert_eq!(
+        dmm_format::detect_layout(source),
+        dmm_format::Layout::Classic
+    );
+    let dmm = dmm_format::from_str(source).expect("Should have parsed");
+    assert_eq!(dmm.size(), (3, 2, 2));
+    assert_eq!(dmm.tile((2, 2, 1)).unwrap()[0].path(), "/turf/open/floor");
+    assert_eq!(dmm.tile((3, 1, 2)).unwrap()[0].path(), "/turf/closed/wall");
+
+    let output = dmm_format::Serializer::new()
+        .layout(dmm_format::Layout::Classic)
+        .to_string(&dmm)
+        .unwrap();
+    assert_eq!(
+        output,
+        source.replace(
+            "icon_state = \"dark\"; dir = 4",
+            "dir = 4; icon_state = \"dark\""
+        )
+    );
+
+    let tgm = dmm_format::to_string(&dmm);
+    assert_eq!(dmm_format::detect_layout(&tgm), dmm_format::Layout::Tgm);
+    assert_eq!(dmm_format::from_str(&tgm).unwrap(), dmm);
+}
+
+#[test]
+fn test_error_position() {
+    let source = r#""a" = (/turf/open/floor,/area/bridge)
+"b" = (/turf/closed/wall{dir = },/area/bridge)
+
+(1,1,1) = {"
+ab
+"}
+"#;
+    let error = dmm_format::from_str(source).unwrap_err();
+    assert!(matches!(
+        error,
+        dmm_format::Error::TrailingCharacters {
+            line: 2,
+            column: 25
+        }
+    ));
+}
+
+#[test]
+fn test_invalid_key() {
+    let source = r#""a" = (/turf/open/floor,/area/bridge)
+"abcd" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+a
+"}
+"#;
+    let error = dmm_format::from_str(source).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid key \"abcd\" at line 2, column 2"
+    );
+}
@@ -0,0 +1,111 @@
+//! Readable diffs of DMM maps for git.
+//!
+//! As an external diff command, it prints the changed tiles with their area and the datums added,
+//! removed or modified:
+//!
+//! ```text
+//! git config diff.dmm.command dmm-diff
+//! echo "*.dmm diff=dmm" >> .gitattributes
+//! ```
+//!
+//! With a single argument, it prints every tile of the map on its own line, which is suitable as
+//! a `textconv` filter (`git config diff.dmm.textconv dmm-diff`) for `git log -p`.
+
+use std::path::Path;
+use std::process;
+
+use dmm::diff::{Change, Diff};
+use dmm::DMM;
+use dmm_format::{datum_to_string, literal_to_string};
+use dmm_tools::{Error, MapFile};
+
+/// Read a map, `/dev/null` being an empty one as git uses it for added and deleted files
+fn read(path: &str) -> Result<DMM, Error> {
+    if Path::new(path) == Path::new("/dev/null") {
+        return Ok(DMM::default());
+    }
+    MapFile::read(path).map(|map| map.dmm)
+}
+
+fn print_tiles(dmm: &DMM) {
+    let mut tiles: Vec<_> = dmm.iter().collect();
+    tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+    for ((x, y, z), datums) in tiles {
+        let datums: Vec<_> = datums.iter().map(datum_to_string).collect();
+        println!("({},{},{}) {}", x, y, z, datums.join(","));
+    }
+}
+
+fn change_to_string(change: &Change) -> String {
+    let value = |value: &Option<_>| {
+        value
+            .as_ref()
+            .map_or("(unset)".to_string(), literal_to_string)
+    };
+    match change {
+        Change::DatumAdded(datum) => format!("+ {}", datum_to_string(datum)),
+        Change::DatumRemoved(datum) => format!("- {}", datum_to_string(datum)),
+        Change::VarEditChanged {
+            path,
+            var,
+            old,
+            new,
+        } => format!("~ {}: {} {} -> {}", path, var, value(old), value(new)),
+        Change::TurfReplaced { old, new } => format!(
+            "~ turf {} -> {}",
+            datum_to_string(old),
+            datum_to_string(new)
+        ),
+        Change::AreaChanged { old, new } => format!(
+            "~ area {} -> {}",
+            datum_to_string(old),
+            datum_to_string(new)
+        ),
+    }
+}
+
+fn print_diff(name: &str, diff: &Diff) {
+    if diff.is_empty() {
+        return;
+    }
+    println!("{}: {} tiles changed", name, diff.tiles.len());
+    for tile in &diff.tiles {
+        let (x, y, z) = tile.coords;
+        println!(
+            "({},{},{}) {}",
+            x,
+            y,
+            z,
+            tile.area.as_deref().unwrap_or("(no area)")
+        );
+        for change in &tile.changes {
+            println!("  {}", change_to_string(change));
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Error> {
+    match args {
+        [path] => print_tiles(&MapFile::read(path)?.dmm),
+        [old, new] => print_diff(new, &dmm::diff(&read(old)?, &read(new)?)),
+        // GIT_EXTERNAL_DIFF: path old-file old-hex old-mode new-file new-hex new-mode
+        [path, old, _, _, new, _, _] => print_diff(path, &dmm::diff(&read(old)?, &read(new)?)),
+        // Renames and copies add the new path and the similarity information
+        [path, old, _, _, new, _, _, _new_path, _] => {
+            print_diff(path, &dmm::diff(&read(old)?, &read(new)?))
+        }
+        _ => {
+            eprintln!("usage: dmm-diff MAP | dmm-diff OLD NEW");
+            process::exit(2);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("dmm-diff: {}", error);
+        process::exit(2);
+    }
+}
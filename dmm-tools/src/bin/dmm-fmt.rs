@@ -0,0 +1,53 @@
+//! Rewrite maps in canonical form.
+//!
+//! With `--check`, the files are left untouched, the ones not in canonical form are listed and
+//! the exit code is 1.
+
+use std::fs;
+use std::process;
+
+use dmm_tools::Error;
+
+/// Returns whether the file was already canonical
+fn format(path: &str, check: bool) -> Result<bool, Error> {
+    let error = |error| Error {
+        path: path.into(),
+        error,
+    };
+    let source = fs::read_to_string(path).map_err(|e| error(dmm_format::Error::Io(e)))?;
+    let canonical = dmm_format::to_canonical_string(&dmm_format::from_str(&source).map_err(error)?);
+    if canonical == source {
+        return Ok(true);
+    }
+    if !check {
+        fs::write(path, canonical).map_err(|e| error(dmm_format::Error::Io(e)))?;
+    }
+    Ok(false)
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let check = args.iter().any(|arg| arg == "--check");
+    args.retain(|arg| arg != "--check");
+    if args.is_empty() {
+        eprintln!("usage: dmm-fmt [--check] MAP...");
+        process::exit(2);
+    }
+
+    let mut exit_code = 0;
+    for path in &args {
+        match format(path, check) {
+            Ok(true) => {}
+            Ok(false) if check => {
+                println!("{}: not in canonical form", path);
+                exit_code = exit_code.max(1);
+            }
+            Ok(false) => println!("{}: formatted", path),
+            Err(error) => {
+                eprintln!("dmm-fmt: {}", error);
+                exit_code = 2;
+            }
+        }
+    }
+    process::exit(exit_code);
+}
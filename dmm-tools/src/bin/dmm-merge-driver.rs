@@ -0,0 +1,61 @@
+//! Git merge driver for DMM maps.
+//!
+//! Register it with
+//!
+//! ```text
+//! git config merge.dmm.driver "dmm-merge-driver %O %A %B"
+//! echo "*.dmm merge=dmm" >> .gitattributes
+//! ```
+//!
+//! The merged map is written to `%A`. Tiles changed differently on both sides keep our version
+//! with a `/obj/merge_conflict_marker` on top, and are listed on stderr.
+
+use std::process;
+
+use dmm::merge::Resolution;
+use dmm::Datum;
+use dmm_tools::{Error, MapFile};
+
+const CONFLICT_MARKER: &str = "/obj/merge_conflict_marker";
+
+/// Returns whether the merge was clean
+fn run(base: &str, ours: &str, theirs: &str) -> Result<bool, Error> {
+    let base_map = MapFile::read(base)?;
+    let our_map = MapFile::read(ours)?;
+    let their_map = MapFile::read(theirs)?;
+
+    let mut merge = dmm::merge(&base_map.dmm, &our_map.dmm, &their_map.dmm);
+    let clean = merge.is_clean();
+    if !clean {
+        eprintln!("{} conflicting tiles:", merge.conflicts().len());
+        for conflict in merge.conflicts() {
+            let (x, y, z) = conflict.coords;
+            eprintln!("  ({},{},{})", x, y, z);
+            for (side, tile) in &[("ours", &conflict.ours), ("theirs", &conflict.theirs)] {
+                let paths: Vec<_> = tile.iter().map(Datum::path).collect();
+                eprintln!("    {:7}{}", side, paths.join(", "));
+            }
+        }
+        merge.resolve_all(&Resolution::Marker(Datum::new(CONFLICT_MARKER)));
+    }
+
+    our_map.write_as(ours, merge.merged())?;
+    Ok(clean)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 3 {
+        eprintln!("usage: dmm-merge-driver BASE OURS THEIRS");
+        process::exit(2);
+    }
+
+    match run(&args[0], &args[1], &args[2]) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("dmm-merge-driver: {}", error);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use dmm_format::Layout;
+use dmm_tools::MapFile;
+
+pub fn run(args: &[String]) -> i32 {
+    let (layout, paths) = match args {
+        [option, layout, paths @ ..] if option == "--to" => match layout.as_str() {
+            "tgm" => (Some(Layout::Tgm), paths),
+            "classic" => (Some(Layout::Classic), paths),
+            _ => return super::usage(),
+        },
+        paths => (None, paths),
+    };
+    let (input, output) = match paths {
+        [input] => (input, input),
+        [input, output] => (input, output),
+        _ => return super::usage(),
+    };
+
+    let result = MapFile::read(input).and_then(|map| {
+        // Switch to the other layout by default
+        let layout = layout.unwrap_or(match map.layout {
+            Layout::Tgm => Layout::Classic,
+            Layout::Classic => Layout::Tgm,
+        });
+        map.write_with_layout(output, &map.dmm, layout)
+    });
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            super::read_error_code(&error)
+        }
+    }
+}
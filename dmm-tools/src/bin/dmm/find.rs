@@ -0,0 +1,111 @@
+use dmm::edit::Filter;
+use dmm_format::datum_to_string;
+use dmm_tools::MapFile;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Found<'a> {
+    file: &'a str,
+    x: u32,
+    y: u32,
+    z: u32,
+    area: Option<String>,
+    datum: String,
+}
+
+/// Parsed arguments: the filter on paths, the `--var` conditions, whether to print JSON, and
+/// the maps
+type Args<'a> = (Filter, Vec<&'a String>, bool, Vec<&'a String>);
+
+fn parse_args(args: &[String]) -> Option<Args<'_>> {
+    let mut path = None;
+    let mut make_filter: fn(String) -> Filter = Filter::path;
+    let mut vars = Vec::new();
+    let mut json = false;
+    let mut maps = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--subtypes" => make_filter = Filter::subtypes_of,
+            "--glob" => make_filter = Filter::glob,
+            "--json" => json = true,
+            "--var" => vars.push(args.next()?),
+            _ if path.is_none() => path = Some(arg.clone()),
+            _ => maps.push(arg),
+        }
+    }
+    if maps.is_empty() {
+        return None;
+    }
+    Some((make_filter(path?), vars, json, maps))
+}
+
+/// Add the `--var` conditions, either `VAR` or `VAR=VALUE`, to `filter`
+fn with_vars(mut filter: Filter, vars: &[&String]) -> Result<Filter, dmm_format::Error> {
+    for var in vars {
+        filter = match var.split_once('=') {
+            Some((var, value)) => filter.with_var(var.trim(), dmm_format::literal_from_str(value)?),
+            None => filter.has_var(var.as_str()),
+        };
+    }
+    Ok(filter)
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let (filter, vars, json, maps) = match parse_args(args) {
+        Some(parsed) => parsed,
+        None => return super::usage(),
+    };
+    let filter = match with_vars(filter, &vars) {
+        Ok(filter) => filter,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            return 2;
+        }
+    };
+
+    let mut code = 0;
+    let mut results = Vec::new();
+    for path in maps {
+        let map = match MapFile::read(path) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("dmm: {}", error);
+                code = code.max(super::read_error_code(&error));
+                continue;
+            }
+        };
+        for found in map.dmm.find(&filter) {
+            let (x, y, z) = found.coords;
+            let found = Found {
+                file: path,
+                x,
+                y,
+                z,
+                area: found.area.map(|area| area.path().to_string()),
+                datum: datum_to_string(found.datum),
+            };
+            if !json {
+                println!(
+                    "{}:({},{},{}) {} {}",
+                    found.file,
+                    x,
+                    y,
+                    z,
+                    found.area.as_deref().unwrap_or("(no area)"),
+                    found.datum
+                );
+            }
+            results.push(found);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    }
+    if code == 0 && results.is_empty() {
+        code = 1;
+    }
+    code
+}
@@ -0,0 +1,124 @@
+use dmm_tools::index::{self, TypeIndex, Use};
+
+const DEFAULT_INDEX: &str = "dmm-index.json";
+
+fn print_uses(uses: &[Use]) {
+    for u in uses {
+        for (x, y, z) in u.coords {
+            println!("{}:({},{},{}) {}", u.map, x, y, z, u.path);
+        }
+    }
+}
+
+fn build(args: &[String]) -> i32 {
+    let (dir, output) = match args {
+        [dir] => (dir, DEFAULT_INDEX),
+        [dir, flag, output] if flag == "-o" => (dir, output.as_str()),
+        _ => return super::usage(),
+    };
+
+    let (index, errors) = match TypeIndex::build(dir) {
+        Ok(built) => built,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            return 2;
+        }
+    };
+    let mut code = 0;
+    for error in &errors {
+        eprintln!("dmm: {}", error);
+        code = code.max(super::read_error_code(error));
+    }
+    if let Err(error) = index.save(output) {
+        eprintln!("dmm: {}", error);
+        return 2;
+    }
+    println!(
+        "{}: {} maps, {} types",
+        output,
+        index.maps().count(),
+        index.types().count()
+    );
+    code
+}
+
+fn query(args: &[String]) -> i32 {
+    let (subtypes, index_path, path) = match args {
+        [flag, index, path] if flag == "--subtypes" => (true, index, path),
+        [index, path] => (false, index, path),
+        _ => return super::usage(),
+    };
+    let index = match TypeIndex::load(index_path) {
+        Ok(index) => index,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            return 2;
+        }
+    };
+
+    let uses = index.uses(path, subtypes);
+    print_uses(&uses);
+    if uses.is_empty() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Report the types of the code unused by the maps, or the uses of the types missing from the
+/// code
+fn compare(args: &[String], unused: bool) -> i32 {
+    let (index_path, code_dir) = match args {
+        [index, code_dir] => (index, code_dir),
+        _ => return super::usage(),
+    };
+    let index = match TypeIndex::load(index_path) {
+        Ok(index) => index,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            return 2;
+        }
+    };
+    let defined = match index::defined_types(code_dir) {
+        Ok(defined) => defined,
+        Err(error) => {
+            eprintln!("dmm: {}", error);
+            return 2;
+        }
+    };
+
+    let found = if unused {
+        let types = index.unused(&defined);
+        for path in &types {
+            println!("{}", path);
+        }
+        !types.is_empty()
+    } else {
+        let uses = index.missing(&defined);
+        print_uses(&uses);
+        if !uses.is_empty() {
+            eprintln!(
+                "dmm: approximate, types only defined by indentation are reported as missing"
+            );
+        }
+        !uses.is_empty()
+    };
+    if found {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    match args.split_first() {
+        Some((command, args)) => match command.as_str() {
+            "build" => build(args),
+            "query" => query(args),
+            "unused" => compare(args, true),
+            "missing" => compare(args, false),
+            _ => super::usage(),
+        },
+        None => super::usage(),
+    }
+}
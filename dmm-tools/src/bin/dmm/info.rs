@@ -0,0 +1,34 @@
+use dmm_format::Layout;
+use dmm_tools::MapFile;
+
+pub fn run(args: &[String]) -> i32 {
+    if args.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = 0;
+    for path in args {
+        let map = match MapFile::read(path) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("dmm: {}", error);
+                code = code.max(super::read_error_code(&error));
+                continue;
+            }
+        };
+        let (width, height, depth) = map.dmm.size();
+        println!("{}", path);
+        println!("  size: {}x{}x{}", width, height, depth);
+        println!("  z-levels: {}", map.dmm.z_levels().len());
+        println!("  dictionary: {} entries", map.dmm.dictionary().len());
+        println!("  key width: {}", map.key_width);
+        println!(
+            "  layout: {}",
+            match map.layout {
+                Layout::Tgm => "tgm",
+                Layout::Classic => "classic",
+            }
+        );
+    }
+    code
+}
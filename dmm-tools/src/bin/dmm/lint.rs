@@ -0,0 +1,91 @@
+use std::fs;
+
+use dmm::lint::{Diagnostic, Severity};
+use dmm_tools::lint_config::{self, LintConfig};
+use dmm_tools::MapFile;
+
+pub fn run(args: &[String]) -> i32 {
+    let (config, maps) = match args {
+        [flag, path, maps @ ..] if flag == "--config" => match LintConfig::read(path) {
+            Ok(config) => (config, maps),
+            Err(error) => {
+                eprintln!("dmm: {}: {}", path, error);
+                return 2;
+            }
+        },
+        _ => (LintConfig::default(), args),
+    };
+    if maps.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = 0;
+    // Rules in the order they run, for grouping
+    let mut rules: Vec<&'static str> = Vec::new();
+    let mut diagnostics: Vec<(&str, Diagnostic)> = Vec::new();
+    for path in maps {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("dmm: {}: {}", path, error);
+                code = 2;
+                continue;
+            }
+        };
+        let map = match MapFile::from_source(&source) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("dmm: {}: {}", path, error);
+                code = code.max(1);
+                continue;
+            }
+        };
+        let suppressions = match lint_config::inline_suppressions(&source) {
+            Ok(suppressions) => suppressions,
+            Err(error) => {
+                eprintln!("dmm: {}: {}", path, error);
+                code = code.max(1);
+                continue;
+            }
+        };
+
+        let linter = config.linter_for(path);
+        for rule in linter.rules() {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        diagnostics.extend(
+            linter
+                .lint(&map.dmm)
+                .into_iter()
+                .filter(|d| {
+                    !config.is_suppressed(path, d)
+                        && !lint_config::is_suppressed_inline(&suppressions, d)
+                })
+                .map(|d| (path.as_str(), d)),
+        );
+    }
+
+    for rule in rules {
+        let found: Vec<_> = diagnostics.iter().filter(|(_, d)| d.rule == rule).collect();
+        if found.is_empty() {
+            continue;
+        }
+        println!("{}: {} found", rule, found.len());
+        for (path, diagnostic) in found {
+            if diagnostic.severity == Severity::Error {
+                code = code.max(1);
+            }
+            let location = match diagnostic.coords {
+                Some((x, y, z)) => format!("{}:({},{},{})", path, x, y, z),
+                None => path.to_string(),
+            };
+            println!(
+                "  {}: {}: {}",
+                location, diagnostic.severity, diagnostic.message
+            );
+        }
+    }
+    code
+}
@@ -0,0 +1,73 @@
+//! Command line tool for DMM maps.
+//!
+//! ```text
+//! dmm info MAP...
+//! dmm convert [--to tgm|classic] MAP [OUTPUT]
+//! dmm validate MAP...
+//! dmm lint [--config FILE] MAP...
+//! dmm stats [--top N] [--json] MAP...
+//! dmm suggest [--min N] [--rules FILE] MAP...
+//! dmm find [--subtypes | --glob] PATH [--var NAME[=VALUE]]... [--json] MAP...
+//! dmm index build DIR [-o INDEX]
+//! dmm index query [--subtypes] INDEX PATH
+//! dmm index unused|missing INDEX CODE_DIR
+//! ```
+//!
+//! The exit code is 0 on success, 1 when a map is invalid or nothing was found, and 2 on usage
+//! or I/O errors.
+
+mod convert;
+mod find;
+mod index;
+mod info;
+mod lint;
+mod stats;
+mod suggest;
+mod validate;
+
+use std::process;
+
+const USAGE: &str = "usage:
+    dmm info MAP...
+    dmm convert [--to tgm|classic] MAP [OUTPUT]
+    dmm validate MAP...
+    dmm lint [--config FILE] MAP...
+    dmm stats [--top N] [--json] MAP...
+    dmm suggest [--min N] [--rules FILE] MAP...
+    dmm find [--subtypes | --glob] PATH [--var NAME[=VALUE]]... [--json] MAP...
+    dmm index build DIR [-o INDEX]
+    dmm index query [--subtypes] INDEX PATH
+    dmm index unused|missing INDEX CODE_DIR";
+
+/// Exit code for an error reading a map: 2 for I/O errors, 1 for invalid maps
+fn read_error_code(error: &dmm_tools::Error) -> i32 {
+    match error.error {
+        dmm_format::Error::Io(_) => 2,
+        _ => 1,
+    }
+}
+
+/// Print the usage and return the exit code for usage errors
+fn usage() -> i32 {
+    eprintln!("{}", USAGE);
+    2
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match args.split_first() {
+        Some((command, args)) => match command.as_str() {
+            "info" => info::run(args),
+            "convert" => convert::run(args),
+            "validate" => validate::run(args),
+            "find" => find::run(args),
+            "lint" => lint::run(args),
+            "stats" => stats::run(args),
+            "suggest" => suggest::run(args),
+            "index" => index::run(args),
+            _ => usage(),
+        },
+        None => usage(),
+    };
+    process::exit(code);
+}
@@ -0,0 +1,141 @@
+use dmm::stats::Stats;
+use dmm_format::literal_to_string;
+use dmm_tools::MapFile;
+use serde::Serialize;
+
+const DEFAULT_TOP: usize = 20;
+
+#[derive(Serialize)]
+struct TypeUsage<'a> {
+    path: &'a str,
+    count: usize,
+    /// Pairs of an area, or `null` for tiles without one, and a count
+    by_area: Vec<(Option<&'a str>, usize)>,
+    by_z: Vec<(u32, usize)>,
+}
+
+#[derive(Serialize)]
+struct VarEditUsage<'a> {
+    path: &'a str,
+    var: &'a str,
+    value: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct MapStats<'a> {
+    file: &'a str,
+    types: Vec<TypeUsage<'a>>,
+    var_edits: Vec<VarEditUsage<'a>>,
+}
+
+impl<'a> MapStats<'a> {
+    fn new(file: &'a str, stats: &'a Stats) -> Self {
+        MapStats {
+            file,
+            types: stats
+                .types
+                .iter()
+                .map(|usage| TypeUsage {
+                    path: &usage.path,
+                    count: usage.count,
+                    by_area: usage
+                        .by_area
+                        .iter()
+                        .map(|(area, count)| (area.as_deref(), *count))
+                        .collect(),
+                    by_z: usage.by_z.iter().map(|(z, count)| (*z, *count)).collect(),
+                })
+                .collect(),
+            var_edits: stats
+                .var_edits
+                .iter()
+                .map(|usage| VarEditUsage {
+                    path: &usage.path,
+                    var: &usage.var,
+                    value: literal_to_string(&usage.value),
+                    count: usage.count,
+                })
+                .collect(),
+        }
+    }
+
+    fn print(&self, top: usize) {
+        println!("{}", self.file);
+        println!("  types:");
+        for usage in self.types.iter().take(top) {
+            let by_z: Vec<_> = usage
+                .by_z
+                .iter()
+                .map(|(z, count)| format!("z{}: {}", z, count))
+                .collect();
+            println!("  {:>8}  {} ({})", usage.count, usage.path, by_z.join(", "));
+            let by_area: Vec<_> = usage
+                .by_area
+                .iter()
+                .map(|(area, count)| format!("{}: {}", area.unwrap_or("(no area)"), count))
+                .collect();
+            println!("            in {}", by_area.join(", "));
+        }
+        println!("  var edits:");
+        for usage in self.var_edits.iter().take(top) {
+            println!(
+                "  {:>8}  {}: {} = {}",
+                usage.count, usage.path, usage.var, usage.value
+            );
+        }
+    }
+}
+
+/// Parsed arguments: the number of entries to print, whether to print JSON, and the maps
+fn parse_args(args: &[String]) -> Option<(usize, bool, Vec<&String>)> {
+    let mut top = DEFAULT_TOP;
+    let mut json = false;
+    let mut maps = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => top = args.next()?.parse().ok()?,
+            "--json" => json = true,
+            _ => maps.push(arg),
+        }
+    }
+    if maps.is_empty() {
+        None
+    } else {
+        Some((top, json, maps))
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let (top, json, maps) = match parse_args(args) {
+        Some(parsed) => parsed,
+        None => return super::usage(),
+    };
+
+    let mut code = 0;
+    let mut stats = Vec::new();
+    for path in maps {
+        match MapFile::read(path) {
+            Ok(map) => stats.push((path, map.dmm.stats())),
+            Err(error) => {
+                eprintln!("dmm: {}", error);
+                code = code.max(super::read_error_code(&error));
+            }
+        }
+    }
+
+    let stats: Vec<_> = stats
+        .iter()
+        .map(|(path, stats)| MapStats::new(path, stats))
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+    } else {
+        for map in &stats {
+            map.print(top);
+        }
+    }
+    code
+}
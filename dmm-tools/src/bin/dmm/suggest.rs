@@ -0,0 +1,71 @@
+use std::fs;
+
+use dmm::suggest::suggest_subtypes;
+use dmm_format::{datum_to_string, rule_to_string, type_definition_to_string};
+use dmm_tools::MapFile;
+
+const DEFAULT_MIN_COUNT: usize = 5;
+
+/// Parsed arguments: the minimum number of instances, the file to write the rules to, and the
+/// maps
+fn parse_args(args: &[String]) -> Option<(usize, Option<&String>, Vec<&String>)> {
+    let mut min_count = DEFAULT_MIN_COUNT;
+    let mut rules = None;
+    let mut maps = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--min" => min_count = args.next()?.parse().ok()?,
+            "--rules" => rules = Some(args.next()?),
+            _ => maps.push(arg),
+        }
+    }
+    if maps.is_empty() {
+        None
+    } else {
+        Some((min_count, rules, maps))
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let (min_count, rules_path, paths) = match parse_args(args) {
+        Some(parsed) => parsed,
+        None => return super::usage(),
+    };
+
+    let mut code = 0;
+    let mut maps = Vec::new();
+    for path in paths {
+        match MapFile::read(path) {
+            Ok(map) => maps.push(map.dmm),
+            Err(error) => {
+                eprintln!("dmm: {}", error);
+                code = code.max(super::read_error_code(&error));
+            }
+        }
+    }
+
+    let suggestions = suggest_subtypes(&maps, min_count);
+    for suggestion in &suggestions {
+        println!(
+            "// {} instances of {}",
+            suggestion.count,
+            datum_to_string(&suggestion.instance)
+        );
+        println!("{}", type_definition_to_string(&suggestion.subtype));
+        println!();
+    }
+
+    if let Some(rules_path) = rules_path {
+        let rules: String = suggestions
+            .iter()
+            .map(|suggestion| format!("{}\n", rule_to_string(&suggestion.rule())))
+            .collect();
+        if let Err(error) = fs::write(rules_path, rules) {
+            eprintln!("dmm: {}: {}", rules_path, error);
+            code = 2;
+        }
+    }
+    code
+}
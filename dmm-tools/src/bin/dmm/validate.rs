@@ -0,0 +1,51 @@
+use dmm::validate::Issue;
+use dmm::Key;
+use dmm_tools::MapFile;
+
+fn describe(issue: &Issue, key_width: usize) -> String {
+    let key = |key: Key| key.to_str_with_width(key_width);
+    match *issue {
+        Issue::UndefinedKey { key: k, .. } => {
+            format!("key \"{}\" is not in the dictionary", key(k))
+        }
+        Issue::UnusedKey(k) => format!("key \"{}\" is not used", key(k)),
+        Issue::MissingTile(_) => "missing tile".to_string(),
+        Issue::NoTurf { key: k, .. } => format!("key \"{}\" has no turf", key(k)),
+        Issue::NoArea { key: k, .. } => format!("key \"{}\" has no area", key(k)),
+        Issue::SeveralTurfs { key: k, .. } => format!("key \"{}\" has several turfs", key(k)),
+        Issue::SeveralAreas { key: k, .. } => format!("key \"{}\" has several areas", key(k)),
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    if args.is_empty() {
+        return super::usage();
+    }
+
+    let mut code = 0;
+    for path in args {
+        let map = match MapFile::read(path) {
+            Ok(map) => map,
+            Err(error) => {
+                println!("{}: error: {}", path, error.error);
+                code = code.max(super::read_error_code(&error));
+                continue;
+            }
+        };
+
+        for issue in map.dmm.validate() {
+            let severity = if issue.is_error() {
+                code = code.max(1);
+                "error"
+            } else {
+                "warning"
+            };
+            match issue.coords() {
+                Some((x, y, z)) => print!("{}:({},{},{}): ", path, x, y, z),
+                None => print!("{}: ", path),
+            }
+            println!("{}: {}", severity, describe(&issue, map.key_width));
+        }
+    }
+    code
+}
@@ -0,0 +1,359 @@
+//! Index of the types used by the maps of a repository.
+//!
+//! The index maps each type path to the map files placing it, relative to the scanned directory,
+//! and to the tiles it is placed on. It is saved as JSON. Comparing it to the types defined by the
+//! DM code finds the unused types and the maps placing types that do not exist.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dmm::DMM;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, MapFile};
+
+#[derive(Debug)]
+pub enum IndexError {
+    Io(PathBuf, std::io::Error),
+    Json(PathBuf, serde_json::Error),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IndexError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            IndexError::Json(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// Tiles of a map where a type is placed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Use<'i> {
+    pub path: &'i str,
+    pub map: &'i str,
+    /// Ordered by z, y then x
+    pub coords: &'i [(u32, u32, u32)],
+}
+
+/// Tiles placing a type, by map
+type MapUses = BTreeMap<String, Vec<(u32, u32, u32)>>;
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TypeIndex {
+    types: BTreeMap<String, MapUses>,
+    maps: BTreeSet<String>,
+}
+
+/// Files with `extension` under `dir`, ordered by path. Hidden files and directories are
+/// skipped.
+fn files_with_extension(dir: &Path, extension: &str) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_unstable_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            files.extend(files_with_extension(&path, extension)?);
+        } else if path.extension().is_some_and(|e| e == extension) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Path of `file` relative to `root`, with `/` separators
+fn relative_name(root: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl TypeIndex {
+    /// Index every `.dmm` file under `root`. Maps that cannot be read are left out of the index
+    /// and returned as errors.
+    pub fn build<P: AsRef<Path>>(root: P) -> Result<(TypeIndex, Vec<Error>), IndexError> {
+        let root = root.as_ref();
+        let files =
+            files_with_extension(root, "dmm").map_err(|e| IndexError::Io(root.to_path_buf(), e))?;
+
+        let mut index = TypeIndex::default();
+        let mut errors = Vec::new();
+        for file in files {
+            match MapFile::read(&file) {
+                Ok(map) => index.add_map(relative_name(root, &file), &map.dmm),
+                Err(error) => errors.push(error),
+            }
+        }
+        Ok((index, errors))
+    }
+
+    /// Add the types placed on `dmm`, replacing a map already indexed under `name`
+    pub fn add_map<S: Into<String>>(&mut self, name: S, dmm: &DMM) {
+        let name = name.into();
+        self.remove_map(&name);
+
+        let mut tiles: Vec<_> = dmm.iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+        for (coords, datums) in tiles {
+            for datum in datums {
+                let coords_of_map = self
+                    .types
+                    .entry(datum.path().to_string())
+                    .or_default()
+                    .entry(name.clone())
+                    .or_default();
+                // Several instances on a tile are listed once
+                if coords_of_map.last() != Some(&coords) {
+                    coords_of_map.push(coords);
+                }
+            }
+        }
+        self.maps.insert(name);
+    }
+
+    pub fn remove_map(&mut self, name: &str) {
+        for maps in self.types.values_mut() {
+            maps.remove(name);
+        }
+        self.types.retain(|_, maps| !maps.is_empty());
+        self.maps.remove(name);
+    }
+
+    /// Indexed maps, relative to the scanned directory
+    pub fn maps(&self) -> impl Iterator<Item = &str> {
+        self.maps.iter().map(String::as_str)
+    }
+
+    /// Types placed on at least one map, ordered by path
+    pub fn types(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+
+    /// Where `path` is placed, with its subtypes if `subtypes` is set. Uses are ordered by type
+    /// then map.
+    pub fn uses(&self, path: &str, subtypes: bool) -> Vec<Use<'_>> {
+        let path = path.trim_end_matches('/');
+        let subtype_prefix = format!("{}/", path);
+        self.types
+            .range(path.to_string()..)
+            .take_while(|(type_path, _)| {
+                type_path.as_str() == path || (subtypes && type_path.starts_with(&subtype_prefix))
+            })
+            .filter(|(type_path, _)| subtypes || type_path.as_str() == path)
+            .flat_map(|(type_path, maps)| {
+                maps.iter().map(move |(map, coords)| Use {
+                    path: type_path,
+                    map,
+                    coords,
+                })
+            })
+            .collect()
+    }
+
+    /// Types of `defined` placed on no map, themselves or through their subtypes
+    pub fn unused<'d>(&self, defined: &'d BTreeSet<String>) -> Vec<&'d str> {
+        defined
+            .iter()
+            .filter(|path| self.uses(path, true).is_empty())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Uses of the types missing from `defined`
+    pub fn missing(&self, defined: &BTreeSet<String>) -> Vec<Use<'_>> {
+        self.types
+            .keys()
+            .filter(|path| !defined.contains(*path))
+            .flat_map(|path| self.uses(path, false))
+            .collect()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), IndexError> {
+        let path = path.as_ref();
+        let json =
+            serde_json::to_string(self).map_err(|e| IndexError::Json(path.to_path_buf(), e))?;
+        fs::write(path, json).map_err(|e| IndexError::Io(path.to_path_buf(), e))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, IndexError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| IndexError::Io(path.to_path_buf(), e))?;
+        serde_json::from_str(&json).map_err(|e| IndexError::Json(path.to_path_buf(), e))
+    }
+}
+
+/// `source` without its block comments, which can be nested and span several lines. The line
+/// breaks of the comments are kept, and `/*` does not start a comment inside a string or after
+/// `//`.
+fn strip_block_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        match (c, next) {
+            ('/', Some('*')) => {
+                chars.next();
+                depth += 1;
+            }
+            ('*', Some('/')) if depth > 0 => {
+                chars.next();
+                depth -= 1;
+            }
+            ('\n', _) => stripped.push(c),
+            _ if depth > 0 => {}
+            ('/', Some('/')) | ('"', _) => {
+                // Copy the rest of the line comment or of the string
+                let end = if c == '"' { '"' } else { '\n' };
+                stripped.push(c);
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    stripped.push(c);
+                    chars.next();
+                    if c == '\\' {
+                        stripped.extend(chars.next_if(|&c| c != '\n'));
+                    } else if c == end {
+                        break;
+                    }
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Types defined by DM code, from the lines of `source` starting with a full type path, such as
+/// `/obj/item/pen` or `/obj/item/pen/fancy{`. Comments are ignored, as are proc and var
+/// definitions. Types only defined by indentation under their parent are not found.
+pub fn dm_type_definitions(source: &str) -> Vec<String> {
+    let mut types = Vec::new();
+    for line in strip_block_comments(source).lines() {
+        if !line.starts_with('/') || line.starts_with("//") {
+            continue;
+        }
+        let end = line
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '/'))
+            .unwrap_or(line.len());
+        let (path, rest) = line.split_at(end);
+        let path = path.trim_end_matches('/');
+        let is_member = path
+            .split('/')
+            .any(|segment| segment == "proc" || segment == "verb" || segment == "var");
+        if path.is_empty() || is_member || rest.trim_start().starts_with(['(', '=']) {
+            continue;
+        }
+        types.push(path.to_string());
+    }
+    types
+}
+
+/// Types defined by the `.dm` files under `root`, with their parent types
+pub fn defined_types<P: AsRef<Path>>(root: P) -> Result<BTreeSet<String>, IndexError> {
+    let root = root.as_ref();
+    let files =
+        files_with_extension(root, "dm").map_err(|e| IndexError::Io(root.to_path_buf(), e))?;
+    let mut types = BTreeSet::new();
+    for file in files {
+        let source = fs::read(&file).map_err(|e| IndexError::Io(file.clone(), e))?;
+        for path in dm_type_definitions(&String::from_utf8_lossy(&source)) {
+            // `/obj/item/pen` also defines `/obj/item` and `/obj`
+            let mut parent = path.as_str();
+            while let Some(end) = parent.rfind('/').filter(|&end| end > 0) {
+                parent = &parent[..end];
+                types.insert(parent.to_string());
+            }
+            types.insert(path);
+        }
+    }
+    Ok(types)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dmm::{Datum, Key};
+
+    #[test]
+    fn test_index() {
+        let dmm = DMM::new(
+            vec![
+                (
+                    Key::new(0),
+                    vec![Datum::new("/obj/item/pen"), Datum::new("/turf/floor")],
+                ),
+                (
+                    Key::new(1),
+                    vec![
+                        Datum::new("/obj/item/pen/fancy"),
+                        Datum::new("/obj/item/pen/fancy"),
+                        Datum::new("/obj/item/pencil"),
+                    ],
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            vec![((1, 1, 1), vec![Key::new(0), Key::new(1), Key::new(1)])]
+                .into_iter()
+                .collect(),
+        );
+        let mut index = TypeIndex::default();
+        index.add_map("maps/a.dmm", &dmm);
+        index.add_map("maps/b.dmm", &dmm.z_level(1).unwrap());
+
+        let uses = index.uses("/obj/item/pen", true);
+        let found: Vec<_> = uses.iter().map(|u| (u.path, u.map)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/obj/item/pen", "maps/a.dmm"),
+                ("/obj/item/pen", "maps/b.dmm"),
+                ("/obj/item/pen/fancy", "maps/a.dmm"),
+                ("/obj/item/pen/fancy", "maps/b.dmm"),
+            ]
+        );
+        assert_eq!(uses[2].coords, &[(1, 2, 1), (1, 3, 1)]);
+        assert_eq!(index.uses("/obj/item/pen", false).len(), 2);
+
+        index.remove_map("maps/b.dmm");
+        assert_eq!(index.maps().collect::<Vec<_>>(), vec!["maps/a.dmm"]);
+
+        let defined: BTreeSet<_> = dm_type_definitions(
+            "/obj/item/pen
+/obj/item/pen/New()
+/obj/item/pen/var/ink = 10
+/obj/item/stamp{
+\tname = \"stamp\"
+}
+/obj/item/stamp/ink{desc = \"/*\"}
+/turf/floor // comment /*
+/*
+/obj/item/pencil
+/* nested */
+/obj/item/pen/fancy
+*/ /obj/item/pen/fancy
+",
+        )
+        .into_iter()
+        .collect();
+        assert_eq!(
+            index.unused(&defined),
+            vec!["/obj/item/stamp", "/obj/item/stamp/ink"]
+        );
+        let missing: Vec<_> = index.missing(&defined).iter().map(|u| u.path).collect();
+        assert_eq!(missing, vec!["/obj/item/pen/fancy", "/obj/item/pencil"]);
+    }
+}
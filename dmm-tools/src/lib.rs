@@ -0,0 +1,80 @@
+//! Helpers shared by the command line tools
+
+pub mod index;
+pub mod lint_config;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dmm::DMM;
+use dmm_format::Layout;
+
+/// Error of a tool, with the file it happened on
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error: dmm_format::Error,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A map read from a file, with the way it was written
+pub struct MapFile {
+    pub dmm: DMM,
+    pub layout: Layout,
+    pub key_width: usize,
+}
+
+impl MapFile {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let error = |error| Error {
+            path: path.to_path_buf(),
+            error,
+        };
+        let source = fs::read_to_string(path).map_err(|e| error(dmm_format::Error::Io(e)))?;
+        Self::from_source(&source).map_err(error)
+    }
+
+    /// Parse the content of a map file
+    pub fn from_source(source: &str) -> Result<Self, dmm_format::Error> {
+        Ok(MapFile {
+            dmm: dmm_format::from_str(source)?,
+            layout: dmm_format::detect_layout(source),
+            key_width: dmm_format::detect_key_width(source).unwrap_or(1),
+        })
+    }
+
+    /// Write `dmm` to `path` in the layout of this map, reusing its keys
+    pub fn write_as<P: AsRef<Path>>(&self, path: P, dmm: &DMM) -> Result<(), Error> {
+        self.write_with_layout(path, dmm, self.layout)
+    }
+
+    /// Write `dmm` to `path` in `layout`, reusing the keys of this map
+    pub fn write_with_layout<P: AsRef<Path>>(
+        &self,
+        path: P,
+        dmm: &DMM,
+        layout: Layout,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        let error = |error| Error {
+            path: path.to_path_buf(),
+            error,
+        };
+        let output = dmm_format::Serializer::new()
+            .layout(layout)
+            .reference(&self.dmm)
+            .key_width(self.key_width)
+            .to_string(dmm)
+            .map_err(error)?;
+        fs::write(path, output).map_err(|e| error(dmm_format::Error::Io(e)))
+    }
+}
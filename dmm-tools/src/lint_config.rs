@@ -0,0 +1,366 @@
+//! Lint profiles, read from a TOML file such as:
+//!
+//! ```toml
+//! # Rules turned off or given another severity, for every map
+//! [rules]
+//! redundant-var-edit = "off"
+//! stacked-duplicates = "error"
+//!
+//! # Profile of the maps matching a glob, applied after the rules above
+//! [[maps]]
+//! glob = "_maps/shuttles/**/*.dmm"
+//! rules = { missing-area = "off" }
+//! allowed = ["/area/shuttle", "/turf", "/obj"]
+//! banned = ["/obj/item/gun"]
+//!
+//! # Findings to ignore, of every rule when `rule` is not given, everywhere on the map when
+//! # `coords` is not given
+//! [[suppress]]
+//! map = "_maps/station/*.dmm"
+//! rule = "multiple-areas"
+//! coords = [[10, 12, 1]]
+//! ```
+//!
+//! Findings can also be suppressed by comments in the map itself:
+//!
+//! ```text
+//! // dmm-lint: ignore multiple-areas (10,12,1) (10,13,1)
+//! // dmm-lint: ignore stacked-duplicates
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use dmm::lint::rules::{self, PathPolicy};
+use dmm::lint::{Diagnostic, Linter, Rule, Severity};
+use glob::{MatchOptions, Pattern};
+use serde::Deserialize;
+
+const COMMENT_PREFIX: &str = "// dmm-lint:";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    InvalidGlob(String),
+    UnknownRule(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Toml(e) => write!(f, "{}", e),
+            ConfigError::InvalidGlob(glob) => write!(f, "invalid glob \"{}\"", glob),
+            ConfigError::UnknownRule(rule) => write!(f, "unknown rule \"{}\"", rule),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MapProfile {
+    /// Glob matched against the path of the map, `**` matching several directories
+    pub glob: String,
+    #[serde(default)]
+    pub rules: BTreeMap<String, Level>,
+    /// Types allowed on the map, with their subtypes. Every type is allowed when empty.
+    #[serde(default)]
+    pub allowed: Vec<String>,
+    /// Types banned from the map, with their subtypes
+    #[serde(default)]
+    pub banned: Vec<String>,
+}
+
+/// Findings to ignore
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    /// Glob of the maps, every map when not given
+    pub map: Option<String>,
+    /// Rule of the findings, every rule when not given
+    pub rule: Option<String>,
+    /// Tiles of the findings, every tile when empty
+    #[serde(default)]
+    pub coords: Vec<(u32, u32, u32)>,
+}
+
+impl Suppression {
+    /// Whether `diagnostic`, found on a map matching `map`, is suppressed. The map is not
+    /// checked when `map` is `None`.
+    fn suppresses(&self, map: Option<&Path>, diagnostic: &Diagnostic) -> bool {
+        let map_matches = match (&self.map, map) {
+            (Some(glob), Some(map)) => glob_matches(glob, map),
+            _ => true,
+        };
+        map_matches
+            && self
+                .rule
+                .as_deref()
+                .is_none_or(|rule| rule == diagnostic.rule)
+            && (self.coords.is_empty()
+                || diagnostic
+                    .coords
+                    .is_some_and(|coords| self.coords.contains(&coords)))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: BTreeMap<String, Level>,
+    #[serde(default)]
+    pub maps: Vec<MapProfile>,
+    #[serde(default)]
+    pub suppress: Vec<Suppression>,
+}
+
+fn glob_options() -> MatchOptions {
+    MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    }
+}
+
+/// Whether `path` matches `glob`, ignoring a leading `./`
+fn glob_matches(glob: &str, path: &Path) -> bool {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    Pattern::new(glob).is_ok_and(|pattern| pattern.matches_path_with(path, glob_options()))
+}
+
+/// Names of the rules a configuration can refer to
+fn known_rules() -> Vec<&'static str> {
+    rules::builtin()
+        .iter()
+        .map(|rule| rule.name())
+        .chain(Some(PathPolicy::default().name()))
+        .collect()
+}
+
+impl LintConfig {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let source = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_str(&source)
+    }
+
+    /// Parse a configuration, checking its rule names and globs
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &str) -> Result<Self, ConfigError> {
+        let config: LintConfig = toml::from_str(source).map_err(ConfigError::Toml)?;
+
+        let known = known_rules();
+        let rule_names = config
+            .rules
+            .keys()
+            .chain(config.maps.iter().flat_map(|profile| profile.rules.keys()))
+            .chain(config.suppress.iter().filter_map(|s| s.rule.as_ref()));
+        if let Some(unknown) = rule_names
+            .into_iter()
+            .find(|name| !known.contains(&name.as_str()))
+        {
+            return Err(ConfigError::UnknownRule(unknown.clone()));
+        }
+
+        let globs = config
+            .maps
+            .iter()
+            .map(|profile| &profile.glob)
+            .chain(config.suppress.iter().filter_map(|s| s.map.as_ref()));
+        for glob in globs {
+            if Pattern::new(glob).is_err() {
+                return Err(ConfigError::InvalidGlob(glob.clone()));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Profiles matching `map`, in the order of the file
+    fn profiles<'c>(&'c self, map: &'c Path) -> impl Iterator<Item = &'c MapProfile> + 'c {
+        self.maps
+            .iter()
+            .filter(move |profile| glob_matches(&profile.glob, map))
+    }
+
+    /// Linter with the built-in rules, configured for `map`
+    pub fn linter_for<P: AsRef<Path>>(&self, map: P) -> Linter {
+        let map = map.as_ref();
+        let mut linter = Linter::default();
+
+        let mut policy = PathPolicy::default();
+        for profile in self.profiles(map) {
+            policy.allowed.extend(profile.allowed.iter().cloned());
+            policy.banned.extend(profile.banned.iter().cloned());
+        }
+        if policy != PathPolicy::default() {
+            linter.add(policy);
+        }
+
+        // The last level given to a rule wins
+        let mut levels = self.rules.clone();
+        for profile in self.profiles(map) {
+            levels.extend(profile.rules.clone());
+        }
+        for (rule, level) in &levels {
+            match level {
+                Level::Off => linter.remove(rule),
+                Level::Warning => linter.set_severity(rule, Severity::Warning),
+                Level::Error => linter.set_severity(rule, Severity::Error),
+            };
+        }
+        linter
+    }
+
+    /// Whether `diagnostic`, found on `map`, is suppressed by the configuration
+    pub fn is_suppressed<P: AsRef<Path>>(&self, map: P, diagnostic: &Diagnostic) -> bool {
+        self.suppress
+            .iter()
+            .any(|suppression| suppression.suppresses(Some(map.as_ref()), diagnostic))
+    }
+}
+
+/// Suppressions from the `// dmm-lint: ignore RULE [(x,y,z)...]` comment lines of a map.
+/// Malformed comments and unknown rules are returned as errors.
+pub fn inline_suppressions(source: &str) -> Result<Vec<Suppression>, String> {
+    let known = known_rules();
+    let mut suppressions = Vec::new();
+    for line in source.lines() {
+        let comment = match line.trim().strip_prefix(COMMENT_PREFIX) {
+            Some(comment) => comment.trim(),
+            None => continue,
+        };
+        let invalid = || format!("invalid comment \"{}\"", line.trim());
+        let rest = comment.strip_prefix("ignore ").ok_or_else(invalid)?.trim();
+        let (rule, coords) = rest.split_at(rest.find('(').unwrap_or(rest.len()));
+        let coords = coords
+            .split(')')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                let numbers: Vec<u32> = c
+                    .strip_prefix('(')?
+                    .split(',')
+                    .map(|n| n.trim().parse().ok())
+                    .collect::<Option<_>>()?;
+                match numbers[..] {
+                    [x, y, z] => Some((x, y, z)),
+                    _ => None,
+                }
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let rule = rule.trim();
+        if rule.is_empty() {
+            return Err(invalid());
+        }
+        if !known.contains(&rule) {
+            return Err(ConfigError::UnknownRule(rule.to_string()).to_string());
+        }
+        suppressions.push(Suppression {
+            map: None,
+            rule: Some(rule.to_string()),
+            coords,
+        });
+    }
+    Ok(suppressions)
+}
+
+/// Whether one of the inline `suppressions` of a map suppresses `diagnostic`
+pub fn is_suppressed_inline(suppressions: &[Suppression], diagnostic: &Diagnostic) -> bool {
+    suppressions
+        .iter()
+        .any(|suppression| suppression.suppresses(None, diagnostic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[rules]
+redundant-var-edit = "off"
+
+[[maps]]
+glob = "_maps/shuttles/**/*.dmm"
+rules = { missing-area = "warning" }
+banned = ["/obj/item/gun"]
+
+[[suppress]]
+map = "_maps/*.dmm"
+rule = "multiple-areas"
+coords = [[1, 2, 1]]
+"#;
+
+    fn diagnostic(rule: &'static str, coords: (u32, u32, u32)) -> Diagnostic {
+        Diagnostic {
+            rule,
+            severity: Severity::Error,
+            coords: Some(coords),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let config = LintConfig::from_str(CONFIG).unwrap();
+        let station: Vec<_> = config.linter_for("./_maps/box.dmm").rules().collect();
+        assert!(!station.contains(&"redundant-var-edit"));
+        assert!(!station.contains(&"path-policy"));
+        let shuttle: Vec<_> = config
+            .linter_for("_maps/shuttles/emergency/pod.dmm")
+            .rules()
+            .collect();
+        assert!(shuttle.contains(&"path-policy"));
+
+        assert!(config.is_suppressed("./_maps/box.dmm", &diagnostic("multiple-areas", (1, 2, 1))));
+        assert!(!config.is_suppressed("_maps/box.dmm", &diagnostic("multiple-areas", (1, 1, 1))));
+        assert!(!config.is_suppressed(
+            "_maps/shuttles/pod.dmm",
+            &diagnostic("multiple-areas", (1, 2, 1))
+        ));
+
+        assert!(matches!(
+            LintConfig::from_str("[rules]\nno-such-rule = \"off\""),
+            Err(ConfigError::UnknownRule(_))
+        ));
+    }
+
+    #[test]
+    fn test_inline_suppressions() {
+        let source = "// dmm-lint: ignore multiple-areas (1,2,1) (3, 4, 1)
+// dmm-lint: ignore stacked-duplicates
+\"a\" = (/turf,/area)
+";
+        let suppressions = inline_suppressions(source).unwrap();
+        assert_eq!(suppressions[0].coords, vec![(1, 2, 1), (3, 4, 1)]);
+        assert!(is_suppressed_inline(
+            &suppressions,
+            &diagnostic("stacked-duplicates", (5, 5, 1))
+        ));
+        assert!(!is_suppressed_inline(
+            &suppressions,
+            &diagnostic("multiple-areas", (5, 5, 1))
+        ));
+        assert!(inline_suppressions("// dmm-lint: ignore multiple-areas (1,2)").is_err());
+        assert_eq!(
+            inline_suppressions("// dmm-lint: ignore no-such-rule"),
+            Err("unknown rule \"no-such-rule\"".to_string())
+        );
+
+        // Only whole comment lines are directives
+        let in_string = "\"a\" = (/obj/item{desc = \"// dmm-lint: oops\"},/turf,/area)\n";
+        assert_eq!(inline_suppressions(in_string), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,340 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const MAP: &str = r#""aa" = (/turf/open/floor,/area/bridge)
+"ab" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+abaa
+aaab
+"}
+"#;
+
+/// Run `dmm` in a directory of its own containing `map.dmm`
+fn dmm(test: &str, map: &str, args: &[&str]) -> (Output, PathBuf) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("cli-{}", test));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("map.dmm"), map).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_dmm"))
+        .current_dir(&dir)
+        .args(args)
+        .output()
+        .unwrap();
+    (output, dir)
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_info() {
+    let (output, _) = dmm("info", MAP, &["info", "map.dmm"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "map.dmm
+  size: 2x2x1
+  z-levels: 1
+  dictionary: 2 entries
+  key width: 2
+  layout: classic
+"
+    );
+
+    // Invalid maps exit with 1, missing files with 2
+    let (output, _) = dmm("info", "garbage", &["info", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(1));
+    let (output, _) = dmm("info", MAP, &["info", "map.dmm", "missing.dmm"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).starts_with("map.dmm\n"));
+}
+
+#[test]
+fn test_convert() {
+    let (output, dir) = dmm("convert", MAP, &["convert", "map.dmm", "tgm.dmm"]);
+    assert!(output.status.success());
+    let tgm = fs::read_to_string(dir.join("tgm.dmm")).unwrap();
+    assert_eq!(dmm_format::detect_layout(&tgm), dmm_format::Layout::Tgm);
+    assert_eq!(dmm_format::detect_key_width(&tgm), Some(2));
+
+    let (output, dir) = dmm("convert", &tgm, &["convert", "--to", "classic", "map.dmm"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("map.dmm")).unwrap(), MAP);
+}
+
+#[test]
+fn test_validate() {
+    let (output, _) = dmm("validate", MAP, &["validate", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let broken = MAP
+        .replace("/turf/closed/wall,", "")
+        .replace("aaab", "aaac");
+    let (output, _) = dmm("validate", &broken, &["validate", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "map.dmm:(1,1,1): error: key \"ab\" has no turf
+map.dmm:(2,2,1): error: key \"ac\" is not in the dictionary
+"
+    );
+
+    let (output, _) = dmm(
+        "validate",
+        &MAP.replace("bridge)\n\n", "bridge\n\n"),
+        &["validate", "map.dmm"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "map.dmm: error: unexpected characters at line 4, column 1\n"
+    );
+}
+
+#[test]
+fn test_find() {
+    let map = MAP.replace(
+        "/turf/closed/wall,",
+        "/obj/machinery/door/airlock/command{req_access = 19},/turf/open/floor,",
+    );
+    let (output, _) = dmm(
+        "find",
+        &map,
+        &[
+            "find",
+            "--subtypes",
+            "/obj/machinery/door",
+            "--var",
+            "req_access=19",
+            "map.dmm",
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "map.dmm:(1,1,1) /area/bridge /obj/machinery/door/airlock/command{req_access = 19}
+map.dmm:(2,2,1) /area/bridge /obj/machinery/door/airlock/command{req_access = 19}
+"
+    );
+
+    let (output, _) = dmm(
+        "find",
+        &map,
+        &["find", "--glob", "/obj/*/command", "--json", "map.dmm"],
+    );
+    let found: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(found.as_array().unwrap().len(), 2);
+    assert_eq!(found[1]["y"], 2);
+    assert_eq!(found[1]["area"], "/area/bridge");
+
+    let (output, _) = dmm("find", &map, &["find", "/obj/machinery/door", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let (output, _) = dmm(
+        "find",
+        &map,
+        &["find", "/obj/item", "--var", "name=list(", "map.dmm"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        "dmm: invalid value list(\n"
+    );
+}
+
+#[test]
+fn test_lint() {
+    let (output, _) = dmm("lint", MAP, &["lint", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let map = MAP
+        .replace(
+            "/turf/closed/wall,",
+            "/obj/item/pen,/obj/item/pen,/turf/closed/wall,",
+        )
+        .replace(",/area/bridge)\n\n", ")\n\n");
+    let (output, _) = dmm("lint", &map, &["lint", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "missing-area: 2 found
+  map.dmm:(1,1,1): error: no area
+  map.dmm:(2,2,1): error: no area
+stacked-duplicates: 2 found
+  map.dmm:(1,1,1): warning: 2 identical /obj/item/pen
+  map.dmm:(2,2,1): warning: 2 identical /obj/item/pen
+"
+    );
+
+    let map = format!("// dmm-lint: ignore no-such-rule\n{}", MAP);
+    let (output, _) = dmm("lint", &map, &["lint", "map.dmm"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        "dmm: map.dmm: unknown rule \"no-such-rule\"\n"
+    );
+}
+
+#[test]
+fn test_lint_config() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli-lint-config");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("lint.toml"),
+        r#"
+[rules]
+missing-area = "warning"
+
+[[maps]]
+glob = "*.dmm"
+banned = ["/turf/closed"]
+
+[[suppress]]
+rule = "path-policy"
+coords = [[1, 1, 1]]
+"#,
+    )
+    .unwrap();
+
+    let map = format!(
+        "// dmm-lint: ignore missing-area (2,2,1)\n{}",
+        MAP.replace(",/area/bridge)\n\n", ")\n\n")
+    );
+    let (output, _) = dmm(
+        "lint-config",
+        &map,
+        &["lint", "--config", "lint.toml", "map.dmm"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "missing-area: 1 found
+  map.dmm:(1,1,1): warning: no area
+path-policy: 1 found
+  map.dmm:(2,2,1): error: /turf/closed/wall is banned
+"
+    );
+}
+
+#[test]
+fn test_stats() {
+    let map = MAP.replace(
+        "/turf/closed/wall,",
+        "/obj/machinery/light{dir = 4},/turf/closed/wall,",
+    );
+    let (output, _) = dmm("stats", &map, &["stats", "--top", "2", "map.dmm"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "map.dmm
+  types:
+         4  /area/bridge (z1: 4)
+            in /area/bridge: 4
+         2  /obj/machinery/light (z1: 2)
+            in /area/bridge: 2
+  var edits:
+         2  /obj/machinery/light: dir = 4
+"
+    );
+
+    let (output, _) = dmm("stats", &map, &["stats", "--json", "map.dmm"]);
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        stats[0]["types"][0]["by_area"],
+        serde_json::json!([["/area/bridge", 4]])
+    );
+}
+
+#[test]
+fn test_suggest() {
+    let map = MAP.replace(
+        "/turf/closed/wall,",
+        "/obj/machinery/light{dir = 4},/turf/closed/wall,",
+    );
+    let (output, dir) = dmm(
+        "suggest",
+        &map,
+        &["suggest", "--min", "2", "--rules", "rules.txt", "map.dmm"],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "// 2 instances of /obj/machinery/light{dir = 4}
+/obj/machinery/light/dir_4
+\tdir = 4
+
+"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("rules.txt")).unwrap(),
+        "/obj/machinery/light{dir = 4} : /obj/machinery/light/dir_4{@OLD; dir = @SKIP}\n"
+    );
+}
+
+#[test]
+fn test_index() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli-index");
+    fs::create_dir_all(dir.join("maps/station")).unwrap();
+    fs::write(dir.join("maps/station/map.dmm"), MAP).unwrap();
+    fs::create_dir_all(dir.join("code")).unwrap();
+    fs::write(
+        dir.join("code/turfs.dm"),
+        "/turf/open/floor\n/turf/closed/wall/r_wall\n/area/bridge\n",
+    )
+    .unwrap();
+
+    let (output, _) = dmm(
+        "index",
+        MAP,
+        &["index", "build", "maps", "-o", "index.json"],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "index.json: 1 maps, 3 types\n");
+
+    fs::write(dir.join("maps/broken.dmm"), "garbage").unwrap();
+    let (output, _) = dmm(
+        "index",
+        MAP,
+        &["index", "build", "maps", "-o", "broken.json"],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "broken.json: 1 maps, 3 types\n");
+    assert!(std::str::from_utf8(&output.stderr)
+        .unwrap()
+        .starts_with("dmm: maps/broken.dmm: "));
+    fs::remove_file(dir.join("maps/broken.dmm")).unwrap();
+
+    let (output, _) = dmm(
+        "index",
+        MAP,
+        &["index", "query", "--subtypes", "index.json", "/turf/closed"],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "station/map.dmm:(1,1,1) /turf/closed/wall
+station/map.dmm:(2,2,1) /turf/closed/wall
+"
+    );
+
+    let (output, _) = dmm("index", MAP, &["index", "unused", "index.json", "code"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "/turf/closed/wall/r_wall\n");
+
+    let (output, _) = dmm("index", MAP, &["index", "missing", "index.json", "code"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    fs::write(dir.join("code/turfs.dm"), "/turf\n\topen/floor\n").unwrap();
+    let (output, _) = dmm("index", MAP, &["index", "missing", "index.json", "code"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains(" /turf/open/floor\n"));
+    assert!(std::str::from_utf8(&output.stderr)
+        .unwrap()
+        .starts_with("dmm: approximate"));
+}
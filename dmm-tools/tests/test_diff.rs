@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const OLD: &str = r#""a" = (/obj/machinery/light{dir = 4},/turf/open/floor,/area/bridge)
+"b" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+ab
+"}
+"#;
+
+const NEW: &str = r#""a" = (/obj/machinery/light{dir = 8},/obj/item,/turf/open/floor,/area/bridge)
+"b" = (/turf/open/floor,/area/hallway)
+
+(1,1,1) = {"
+ab
+"}
+"#;
+
+/// Run `dmm-diff` in a directory of its own containing `old.dmm` and `new.dmm`
+fn dmm_diff(test: &str, args: &[&str]) -> String {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("diff-{}", test));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("old.dmm"), OLD).unwrap();
+    fs::write(dir.join("new.dmm"), NEW).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_dmm-diff"))
+        .current_dir(&dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(
+        dmm_diff("diff", &["old.dmm", "new.dmm"]),
+        "new.dmm: 2 tiles changed
+(1,1,1) /area/bridge
+  ~ /obj/machinery/light: dir 4 -> 8
+  + /obj/item
+(2,1,1) /area/hallway
+  ~ area /area/bridge -> /area/hallway
+  ~ turf /turf/closed/wall -> /turf/open/floor
+"
+    );
+
+    let git_args = [
+        "maps/station.dmm",
+        "/dev/null",
+        "0000000",
+        "100644",
+        "new.dmm",
+        "1234567",
+        "100644",
+    ];
+    assert!(dmm_diff("git", &git_args).starts_with("maps/station.dmm: 2 tiles changed\n(1,1,1)"));
+
+    let rename_args = [
+        "maps/station.dmm",
+        "old.dmm",
+        "0000000",
+        "100644",
+        "new.dmm",
+        "1234567",
+        "100644",
+        "maps/station2.dmm",
+        "similarity index 90%\nrename from maps/station.dmm\nrename to maps/station2.dmm\n",
+    ];
+    assert!(dmm_diff("rename", &rename_args).starts_with("maps/station.dmm: 2 tiles changed\n"));
+}
+
+#[test]
+fn test_textconv() {
+    assert_eq!(
+        dmm_diff("textconv", &["old.dmm"]),
+        "(1,1,1) /obj/machinery/light{dir = 4},/turf/open/floor,/area/bridge
+(2,1,1) /turf/closed/wall,/area/bridge
+"
+    );
+}
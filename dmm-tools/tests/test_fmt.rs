@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SOURCE: &str = r#""a" = (/area/bridge,/turf/open/floor)
+
+(1,1,1) = {"
+aa
+"}
+"#;
+
+fn dmm_fmt(args: &[&str], dir: &Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_dmm-fmt"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_fmt() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fmt");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("map.dmm"), SOURCE).unwrap();
+
+    let output = dmm_fmt(&["--check", "map.dmm"], &dir);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "map.dmm: not in canonical form\n"
+    );
+    assert_eq!(fs::read_to_string(dir.join("map.dmm")).unwrap(), SOURCE);
+
+    assert!(dmm_fmt(&["map.dmm"], &dir).status.success());
+    assert!(dmm_format::is_canonical(&fs::read_to_string(dir.join("map.dmm")).unwrap()).unwrap());
+    assert!(dmm_fmt(&["--check", "map.dmm"], &dir).status.success());
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const BASE: &str = r#""a" = (/turf/open/floor,/area/bridge)
+"b" = (/turf/closed/wall,/area/bridge)
+
+(1,1,1) = {"
+bbb
+bab
+"}
+"#;
+
+fn write_versions(name: &str, ours: &str, theirs: &str) -> [PathBuf; 3] {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let paths = [
+        dir.join("base.dmm"),
+        dir.join("ours.dmm"),
+        dir.join("theirs.dmm"),
+    ];
+    for (path, content) in paths.iter().zip(&[BASE, ours, theirs]) {
+        fs::write(path, content).unwrap();
+    }
+    paths
+}
+
+fn merge_driver(paths: &[PathBuf; 3]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_dmm-merge-driver"))
+        .args(paths)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_clean_merge() {
+    let paths = write_versions(
+        "clean",
+        &BASE.replace("bbb\nbab", "abb\nbab"),
+        &BASE.replace("bbb\nbab", "bbb\nbaa"),
+    );
+
+    let output = merge_driver(&paths);
+    assert!(output.status.success());
+    // Written back in the classic layout with the keys of ours
+    assert_eq!(
+        fs::read_to_string(&paths[1]).unwrap(),
+        BASE.replace("bbb\nbab", "abb\nbaa")
+    );
+}
+
+#[test]
+fn test_conflict() {
+    let paths = write_versions(
+        "conflict",
+        &BASE.replace(
+            "\"a\" = (/turf/open/floor",
+            "\"a\" = (/turf/open/floor/wood",
+        ),
+        &BASE.replace(
+            "\"a\" = (/turf/open/floor",
+            "\"a\" = (/turf/open/floor/carpet",
+        ),
+    );
+
+    let output = merge_driver(&paths);
+    assert_eq!(output.status.code(), Some(1));
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(report.contains("(2,2,1)"));
+    assert!(report.contains("/turf/open/floor/carpet"));
+
+    let merged = dmm_format::from_str(&fs::read_to_string(&paths[1]).unwrap()).unwrap();
+    assert_eq!(
+        merged.tile((2, 2, 1)).unwrap()[..2],
+        [
+            dmm::Datum::new("/obj/merge_conflict_marker"),
+            dmm::Datum::new("/turf/open/floor/wood")
+        ]
+    );
+}
@@ -0,0 +1,144 @@
+//! Extents and contiguity of areas.
+//!
+//! Tiles are grouped by the path of their `/area` datum. An area is contiguous when its tiles
+//! form a single region connected in the cardinal directions, on a single z-level.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::connected_groups;
+use crate::DMM;
+
+/// Connected part of an area
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Region {
+    /// First tile of the region, ordered by z, y then x
+    pub first: (u32, u32, u32),
+    pub tiles: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Area {
+    pub path: String,
+    pub tiles: usize,
+    /// Smallest and largest coordinates of the tiles on each axis
+    pub min: (u32, u32, u32),
+    pub max: (u32, u32, u32),
+    pub z_levels: BTreeSet<u32>,
+    /// Connected regions, the largest first
+    pub regions: Vec<Region>,
+}
+
+impl Area {
+    pub fn is_contiguous(&self) -> bool {
+        self.regions.len() == 1
+    }
+}
+
+impl DMM {
+    /// Every area of the map, ordered by path. Tiles without an area are ignored.
+    pub fn areas(&self) -> Vec<Area> {
+        let mut tiles: BTreeMap<&str, HashSet<(u32, u32, u32)>> = BTreeMap::new();
+        for (coords, datums) in self.iter() {
+            if let Some(area) = datums.iter().find(|d| d.is_area()) {
+                tiles.entry(area.path()).or_default().insert(coords);
+            }
+        }
+
+        tiles
+            .into_iter()
+            .map(|(path, tiles)| {
+                let (mut min, mut max) = ((u32::MAX, u32::MAX, u32::MAX), (0, 0, 0));
+                for &(x, y, z) in &tiles {
+                    min = (min.0.min(x), min.1.min(y), min.2.min(z));
+                    max = (max.0.max(x), max.1.max(y), max.2.max(z));
+                }
+                let mut regions: Vec<_> = connected_groups(&tiles)
+                    .into_iter()
+                    .map(|group| Region {
+                        first: group[0],
+                        tiles: group.len(),
+                    })
+                    .collect();
+                // Stable, so regions of the same size stay ordered by location
+                regions.sort_by_key(|region| std::cmp::Reverse(region.tiles));
+
+                Area {
+                    path: path.to_string(),
+                    tiles: tiles.len(),
+                    min,
+                    max,
+                    z_levels: tiles.iter().map(|&(_, _, z)| z).collect(),
+                    regions,
+                }
+            })
+            .collect()
+    }
+
+    /// Areas made of several disconnected regions
+    pub fn split_areas(&self) -> Vec<Area> {
+        self.areas()
+            .into_iter()
+            .filter(|area| !area.is_contiguous())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Datum};
+
+    #[test]
+    fn test_areas() {
+        // b a b
+        // b a a
+        let a = || vec![Datum::new("/turf/a"), Datum::new("/area/a")];
+        let b = || vec![Datum::new("/turf/a"), Datum::new("/area/b")];
+        let dmm = test_map(&[
+            ((1, 1, 1), b()),
+            ((2, 1, 1), a()),
+            ((3, 1, 1), b()),
+            ((1, 2, 1), b()),
+            ((2, 2, 1), a()),
+            ((3, 2, 1), a()),
+            ((1, 1, 2), vec![Datum::new("/turf/a")]),
+        ]);
+
+        let areas = dmm.areas();
+        assert_eq!(
+            areas,
+            vec![
+                Area {
+                    path: "/area/a".to_string(),
+                    tiles: 3,
+                    min: (2, 1, 1),
+                    max: (3, 2, 1),
+                    z_levels: vec![1].into_iter().collect(),
+                    regions: vec![Region {
+                        first: (2, 1, 1),
+                        tiles: 3
+                    }],
+                },
+                Area {
+                    path: "/area/b".to_string(),
+                    tiles: 3,
+                    min: (1, 1, 1),
+                    max: (3, 2, 1),
+                    z_levels: vec![1].into_iter().collect(),
+                    regions: vec![
+                        Region {
+                            first: (1, 1, 1),
+                            tiles: 2
+                        },
+                        Region {
+                            first: (3, 1, 1),
+                            tiles: 1
+                        },
+                    ],
+                },
+            ]
+        );
+        assert!(areas[0].is_contiguous());
+        assert_eq!(dmm.split_areas()[0].path, "/area/b");
+    }
+}
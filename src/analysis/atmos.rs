@@ -0,0 +1,323 @@
+//! Atmospherics pipe networks.
+//!
+//! Pipes (`/obj/machinery/atmospherics/pipe`) and components
+//! (`/obj/machinery/atmospherics/components`) have ends in the directions given by their shape
+//! and `dir` var. Facing ends of two tiles connect when they are on the same `piping_layer`,
+//! read from the var or from a `/layerN` subtype. Pipes connected together form a network, and
+//! components are listed on the networks of the pipes they connect to.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::{reverse, step, Placed, UnionFind, EAST, NORTH, SOUTH, WEST};
+use crate::{Literal, DMM};
+
+const PIPE: &str = "/obj/machinery/atmospherics/pipe";
+const COMPONENT: &str = "/obj/machinery/atmospherics/components";
+const DEFAULT_PIPING_LAYER: i64 = 3;
+const CARDINALS: [u8; 4] = [NORTH, SOUTH, EAST, WEST];
+
+/// End of a pipe or component connected to nothing
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PipeEnd {
+    pub coords: (u32, u32, u32),
+    pub dir: u8,
+    pub layer: i64,
+}
+
+/// End of a pipe or component facing only ends of another layer
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayerMismatch {
+    pub end: PipeEnd,
+    /// Layers of the facing ends
+    pub other_layers: BTreeSet<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipeNetwork<'d> {
+    pub pipes: Vec<Placed<'d>>,
+    /// Components connected to the pipes, such as vents, scrubbers and pumps
+    pub components: Vec<Placed<'d>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AtmosAnalysis<'d> {
+    /// Networks ordered by their first pipe, ordered by z, y then x
+    pub networks: Vec<PipeNetwork<'d>>,
+    pub dangling_ends: Vec<PipeEnd>,
+    pub layer_mismatches: Vec<LayerMismatch>,
+}
+
+struct Device<'d> {
+    placed: Placed<'d>,
+    is_pipe: bool,
+    /// Directions of the ends
+    ends: u8,
+    layer: i64,
+    /// Connects to every layer, like layer manifolds
+    all_layers: bool,
+    /// Connects to what is around, like smart pipes, so missing connections are expected
+    automatic: bool,
+}
+
+impl Device<'_> {
+    fn connects_to(&self, other: &Device) -> bool {
+        self.all_layers || other.all_layers || self.layer == other.layer
+    }
+}
+
+/// `dir` turned 90 degrees counterclockwise
+fn turn_left(dir: u8) -> u8 {
+    match dir {
+        NORTH => WEST,
+        WEST => SOUTH,
+        SOUTH => EAST,
+        _ => NORTH,
+    }
+}
+
+fn device(placed: Placed<'_>) -> Option<Device<'_>> {
+    let datum = placed.datum;
+    let is_pipe = datum.is_subtype_of(PIPE);
+    if !is_pipe && !datum.is_subtype_of(COMPONENT) {
+        return None;
+    }
+
+    let number_var = |var| datum.var_edit(var).and_then(Literal::as_number);
+    let dir = number_var("dir").map_or(SOUTH, |dir| dir as u8);
+    let segments: Vec<_> = datum.path().split('/').collect();
+    let has = |segment| segments.contains(&segment);
+    let straight = dir | reverse(dir);
+
+    let (ends, all_layers, automatic) = if is_pipe {
+        if has("smart") {
+            (NORTH | SOUTH | EAST | WEST, false, true)
+        } else if has("manifold4w") {
+            (NORTH | SOUTH | EAST | WEST, false, false)
+        } else if has("manifold") {
+            ((NORTH | SOUTH | EAST | WEST) & !dir, false, false)
+        } else if has("layer_manifold") {
+            (straight, true, false)
+        } else if dir.count_ones() == 2 {
+            // Bent pipe
+            (dir, false, false)
+        } else {
+            (straight, false, false)
+        }
+    } else if has("binary") {
+        (straight, false, false)
+    } else if has("trinary") {
+        let side = if number_var("flipped").unwrap_or(0) != 0 {
+            reverse(turn_left(dir))
+        } else {
+            turn_left(dir)
+        };
+        ((NORTH | SOUTH | EAST | WEST) & !side, false, false)
+    } else {
+        (dir, false, false)
+    };
+
+    let layer_subtype = segments
+        .iter()
+        .find_map(|segment| segment.strip_prefix("layer")?.parse().ok());
+    Some(Device {
+        placed,
+        is_pipe,
+        ends,
+        layer: number_var("piping_layer")
+            .or(layer_subtype)
+            .unwrap_or(DEFAULT_PIPING_LAYER),
+        all_layers,
+        automatic,
+    })
+}
+
+impl DMM {
+    /// Rebuild the pipe networks of the map
+    pub fn pipe_networks(&self) -> AtmosAnalysis<'_> {
+        let mut tiles: Vec<_> = self.iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+
+        let devices: Vec<_> = tiles
+            .iter()
+            .flat_map(|&(coords, datums)| {
+                datums
+                    .iter()
+                    .filter_map(move |datum| device(Placed { coords, datum }))
+            })
+            .collect();
+        let mut devices_at: HashMap<_, Vec<usize>> = HashMap::new();
+        for (index, device) in devices.iter().enumerate() {
+            devices_at
+                .entry(device.placed.coords)
+                .or_default()
+                .push(index);
+        }
+
+        let mut sets = UnionFind::new(devices.len());
+        // Components connected to each pipe
+        let mut attached: Vec<(usize, usize)> = Vec::new();
+        let mut analysis = AtmosAnalysis::default();
+        for (index, device) in devices.iter().enumerate() {
+            let coords = device.placed.coords;
+            for &dir in CARDINALS.iter().filter(|&&dir| device.ends & dir != 0) {
+                let end = PipeEnd {
+                    coords,
+                    dir,
+                    layer: device.layer,
+                };
+                let facing: Vec<usize> = step(coords, dir)
+                    .and_then(|next| devices_at.get(&next))
+                    .map(|indices| {
+                        indices
+                            .iter()
+                            .copied()
+                            .filter(|&other| devices[other].ends & reverse(dir) != 0)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let connected: Vec<_> = facing
+                    .iter()
+                    .copied()
+                    .filter(|&other| device.connects_to(&devices[other]))
+                    .collect();
+                for &other in &connected {
+                    match (device.is_pipe, devices[other].is_pipe) {
+                        (true, true) => sets.union(index, other),
+                        (true, false) => attached.push((index, other)),
+                        _ => {}
+                    }
+                }
+
+                if !connected.is_empty() || device.automatic {
+                    continue;
+                }
+                if facing.is_empty() {
+                    analysis.dangling_ends.push(end);
+                } else {
+                    analysis.layer_mismatches.push(LayerMismatch {
+                        end,
+                        other_layers: facing.iter().map(|&other| devices[other].layer).collect(),
+                    });
+                }
+            }
+        }
+
+        let mut components_of_root: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+        for (pipe, component) in attached {
+            components_of_root
+                .entry(sets.find(pipe))
+                .or_default()
+                .insert(component);
+        }
+        analysis.networks = sets
+            .groups()
+            .into_iter()
+            .filter(|group| devices[group[0]].is_pipe)
+            .map(|group| {
+                let components = components_of_root
+                    .remove(&sets.find(group[0]))
+                    .unwrap_or_default();
+                PipeNetwork {
+                    pipes: group.iter().map(|&i| devices[i].placed).collect(),
+                    components: components.into_iter().map(|i| devices[i].placed).collect(),
+                }
+            })
+            .collect();
+        analysis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Datum};
+
+    fn device(path: &str, vars: &[(&str, i64)]) -> Datum {
+        Datum::with_var_edits(
+            path,
+            vars.iter()
+                .map(|&(var, value)| (var.to_string(), Literal::Number(value)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_pipe_networks() {
+        let pipe = |vars: &[(&str, i64)]| device("/obj/machinery/atmospherics/pipe/simple", vars);
+        // vent - pipe - pump - pipe - pipe on layer 2 - manifold on layer 4
+        let tiles = vec![
+            vec![device(
+                "/obj/machinery/atmospherics/components/unary/vent_pump",
+                &[("dir", EAST as i64)],
+            )],
+            vec![pipe(&[("dir", EAST as i64)])],
+            vec![device(
+                "/obj/machinery/atmospherics/components/binary/pump",
+                &[("dir", EAST as i64)],
+            )],
+            vec![pipe(&[("dir", EAST as i64)])],
+            vec![pipe(&[("dir", EAST as i64), ("piping_layer", 2)])],
+            vec![device(
+                "/obj/machinery/atmospherics/pipe/manifold/supply/layer4",
+                &[("dir", NORTH as i64)],
+            )],
+        ];
+        let tiles: Vec<_> = (1..).map(|x| (x, 1, 1)).zip(tiles).collect();
+        let dmm = test_map(&tiles);
+
+        let analysis = dmm.pipe_networks();
+        let networks: Vec<(Vec<_>, Vec<_>)> = analysis
+            .networks
+            .iter()
+            .map(|network| {
+                (
+                    network.pipes.iter().map(|p| p.coords.0).collect(),
+                    network.components.iter().map(|c| c.coords.0).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            networks,
+            vec![
+                (vec![2], vec![1, 3]),
+                (vec![4], vec![3]),
+                (vec![5], vec![]),
+                (vec![6], vec![]),
+            ]
+        );
+
+        let mismatch = |x, dir, layer, other| LayerMismatch {
+            end: PipeEnd {
+                coords: (x, 1, 1),
+                dir,
+                layer,
+            },
+            other_layers: vec![other].into_iter().collect(),
+        };
+        assert_eq!(
+            analysis.layer_mismatches,
+            vec![
+                mismatch(4, EAST, 3, 2),
+                mismatch(5, EAST, 2, 4),
+                mismatch(5, WEST, 2, 3),
+                mismatch(6, WEST, 4, 2),
+            ]
+        );
+        assert_eq!(
+            analysis.dangling_ends,
+            vec![
+                PipeEnd {
+                    coords: (6, 1, 1),
+                    dir: SOUTH,
+                    layer: 4
+                },
+                PipeEnd {
+                    coords: (6, 1, 1),
+                    dir: EAST,
+                    layer: 4
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Analyses of the content of maps.
+
+pub mod areas;
+pub mod atmos;
+pub mod pathfinding;
+pub mod power;
+pub mod rooms;
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::Datum;
+
+/// BYOND directions, as used by the `dir` var. North is up in the file, towards `y = 1`.
+pub const NORTH: u8 = 1;
+pub const SOUTH: u8 = 2;
+pub const EAST: u8 = 4;
+pub const WEST: u8 = 8;
+
+/// A datum placed on a tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placed<'d> {
+    pub coords: (u32, u32, u32),
+    pub datum: &'d Datum,
+}
+
+/// Direction opposite to `dir`, diagonals included
+pub fn reverse(dir: u8) -> u8 {
+    let vertical = match dir & (NORTH | SOUTH) {
+        NORTH => SOUTH,
+        SOUTH => NORTH,
+        _ => 0,
+    };
+    let horizontal = match dir & (EAST | WEST) {
+        EAST => WEST,
+        WEST => EAST,
+        _ => 0,
+    };
+    vertical | horizontal
+}
+
+/// Tile next to `coords` in `dir`, diagonals included, if it is inside the map bounds
+pub fn step((x, y, z): (u32, u32, u32), dir: u8) -> Option<(u32, u32, u32)> {
+    let y = match dir & (NORTH | SOUTH) {
+        NORTH => y.checked_sub(1)?,
+        SOUTH => y + 1,
+        _ => y,
+    };
+    let x = match dir & (EAST | WEST) {
+        EAST => x + 1,
+        WEST => x.checked_sub(1)?,
+        _ => x,
+    };
+    if x == 0 || y == 0 {
+        None
+    } else {
+        Some((x, y, z))
+    }
+}
+
+/// Disjoint sets of indices, to group connected objects
+pub(crate) struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        UnionFind {
+            parents: (0..len).collect(),
+        }
+    }
+
+    pub(crate) fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut index = index;
+        while self.parents[index] != root {
+            let next = self.parents[index];
+            self.parents[index] = root;
+            index = next;
+        }
+        root
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a] = b;
+    }
+
+    /// Indices grouped by set, each group and the groups in ascending order
+    pub(crate) fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_of_root = std::collections::HashMap::new();
+        for index in 0..self.parents.len() {
+            let root = self.find(index);
+            let group = *group_of_root.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(index);
+        }
+        groups
+    }
+}
+
+/// Neighbours of a tile on the same z-level, in the four cardinal directions
+pub(crate) fn cardinal_neighbours(
+    coords: (u32, u32, u32),
+) -> impl Iterator<Item = (u32, u32, u32)> {
+    vec![NORTH, SOUTH, EAST, WEST]
+        .into_iter()
+        .filter_map(move |dir| step(coords, dir))
+}
+
+/// Split `tiles` into groups of tiles connected in the cardinal directions. Each group is
+/// ordered by z, y then x, and the groups by their first tile.
+pub(crate) fn connected_groups(tiles: &HashSet<(u32, u32, u32)>) -> Vec<Vec<(u32, u32, u32)>> {
+    let mut sorted: Vec<_> = tiles.iter().copied().collect();
+    sorted.sort_unstable_by_key(|&(x, y, z)| (z, y, x));
+
+    let mut visited = HashSet::new();
+    let mut groups = Vec::new();
+    for start in sorted {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut group = vec![start];
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(tile) = queue.pop_front() {
+            for neighbour in cardinal_neighbours(tile) {
+                if tiles.contains(&neighbour) && visited.insert(neighbour) {
+                    group.push(neighbour);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        group.sort_unstable_by_key(|&(x, y, z)| (z, y, x));
+        groups.push(group);
+    }
+    groups
+}
@@ -0,0 +1,215 @@
+//! Walkable paths on a z-level.
+//!
+//! A tile is passable when it holds a datum of a passing type, such as a door, or otherwise when
+//! it holds no datum of a blocking type, such as a wall or a grille. Paths go from tile to tile in
+//! the cardinal directions. Directional windows are not handled: they block their whole tile when
+//! they are listed as blocking.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::cardinal_neighbours;
+use crate::{Datum, DMM};
+
+/// Types deciding whether a tile can be walked on, with their subtypes
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathOptions {
+    pub blocking: Vec<String>,
+    /// Types making a tile passable even if it holds a blocking type
+    pub passing: Vec<String>,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            blocking: [
+                "/turf/closed",
+                "/turf/simulated/wall",
+                "/turf/unsimulated/wall",
+                "/turf/open/space",
+                "/turf/space",
+                "/obj/structure/grille",
+                "/obj/structure/window",
+                "/obj/effect/spawner/structure/window",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .collect(),
+            passing: vec!["/obj/machinery/door".to_string()],
+        }
+    }
+}
+
+impl PathOptions {
+    pub fn is_passable(&self, tile: &[Datum]) -> bool {
+        let is_any = |paths: &[String]| {
+            tile.iter()
+                .any(|datum| paths.iter().any(|path| datum.is_subtype_of(path)))
+        };
+        is_any(&self.passing) || !is_any(&self.blocking)
+    }
+}
+
+/// Tile each reached tile was reached from, with its number of steps from the start
+type Reached = HashMap<(u32, u32, u32), ((u32, u32, u32), usize)>;
+
+/// Breadth-first search from `from`, until `to` is reached if it is given
+fn search(
+    dmm: &DMM,
+    from: (u32, u32, u32),
+    to: Option<(u32, u32, u32)>,
+    options: &PathOptions,
+) -> Reached {
+    let tiles: HashMap<_, _> = dmm.iter().collect();
+    let passable = |coords| {
+        tiles
+            .get(&coords)
+            .is_some_and(|tile| options.is_passable(tile))
+    };
+    let mut reached = HashMap::new();
+    if !passable(from) {
+        return reached;
+    }
+    reached.insert(from, (from, 0));
+    let mut queue = VecDeque::from(vec![from]);
+    while let Some(tile) = queue.pop_front() {
+        if Some(tile) == to {
+            break;
+        }
+        let steps = reached[&tile].1 + 1;
+        for neighbour in cardinal_neighbours(tile) {
+            if !reached.contains_key(&neighbour) && passable(neighbour) {
+                reached.insert(neighbour, (tile, steps));
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    reached
+}
+
+impl DMM {
+    /// Shortest walkable path from `from` to `to`, both included, if there is one
+    pub fn find_path(
+        &self,
+        from: (u32, u32, u32),
+        to: (u32, u32, u32),
+        options: &PathOptions,
+    ) -> Option<Vec<(u32, u32, u32)>> {
+        let reached = search(self, from, Some(to), options);
+        if !reached.contains_key(&to) {
+            return None;
+        }
+        let mut path = vec![to];
+        let mut current = to;
+        while current != from {
+            current = reached[&current].0;
+            path.push(current);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn is_reachable(
+        &self,
+        from: (u32, u32, u32),
+        to: (u32, u32, u32),
+        options: &PathOptions,
+    ) -> bool {
+        search(self, from, Some(to), options).contains_key(&to)
+    }
+
+    /// Number of steps from `from` to the closest tile of each area of its z-level, `None` for
+    /// the areas that cannot be reached
+    pub fn reachable_areas(
+        &self,
+        from: (u32, u32, u32),
+        options: &PathOptions,
+    ) -> BTreeMap<String, Option<usize>> {
+        let reached = search(self, from, None, options);
+        let mut areas = BTreeMap::new();
+        for (coords, tile) in self.iter().filter(|((_, _, z), _)| *z == from.2) {
+            for area in tile.iter().filter(|d| d.is_area()) {
+                let closest = areas.entry(area.path().to_string()).or_insert(None);
+                if let Some(&(_, steps)) = reached.get(&coords) {
+                    *closest = Some(closest.map_or(steps, |c: usize| c.min(steps)));
+                }
+            }
+        }
+        areas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    /// Map drawn with `#` for walls, `.` for floors, `D` for doors and `+` for grilles. Tiles
+    /// left of `|` are in the hallway, the others in the bridge.
+    fn map(rows: &[&str]) -> DMM {
+        let floor = || Datum::new("/turf/open/floor");
+        let content = |c| match c {
+            '#' => vec![Datum::new("/turf/closed/wall")],
+            '.' => vec![floor()],
+            'D' => vec![Datum::new("/obj/machinery/door/airlock"), floor()],
+            _ => vec![Datum::new("/obj/structure/grille"), floor()],
+        };
+        let mut tiles = Vec::new();
+        for (y, row) in (1..).zip(rows) {
+            let (hallway, bridge) = row.split_at(row.find('|').unwrap());
+            let row = hallway
+                .chars()
+                .map(|c| (c, "/area/hallway"))
+                .chain(bridge[1..].chars().map(|c| (c, "/area/bridge")));
+            for (x, (c, area)) in (1..).zip(row) {
+                let mut datums = content(c);
+                datums.push(Datum::new(area));
+                tiles.push(((x, y, 1), datums));
+            }
+        }
+        test_map(&tiles)
+    }
+
+    #[test]
+    fn test_pathfinding() {
+        let dmm = map(&[
+            "...#|..", //
+            ".#.D|..", //
+            ".#+#|..",
+        ]);
+        let options = PathOptions::default();
+        assert_eq!(
+            dmm.find_path((1, 3, 1), (6, 1, 1), &options),
+            Some(vec![
+                (1, 3, 1),
+                (1, 2, 1),
+                (1, 1, 1),
+                (2, 1, 1),
+                (3, 1, 1),
+                (3, 2, 1),
+                (4, 2, 1),
+                (5, 2, 1),
+                (5, 1, 1),
+                (6, 1, 1),
+            ])
+        );
+        assert!(!dmm.is_reachable((1, 1, 1), (3, 3, 1), &options));
+        assert_eq!(
+            dmm.find_path((1, 1, 1), (1, 1, 1), &options),
+            Some(vec![(1, 1, 1)])
+        );
+
+        let areas = dmm.reachable_areas((1, 3, 1), &options);
+        assert_eq!(areas["/area/hallway"], Some(0));
+        assert_eq!(areas["/area/bridge"], Some(7));
+
+        let mut locked = PathOptions {
+            passing: vec![],
+            ..PathOptions::default()
+        };
+        locked.blocking.push("/obj/machinery/door".to_string());
+        assert_eq!(
+            dmm.reachable_areas((1, 3, 1), &locked)["/area/bridge"],
+            None
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Power networks.
+//!
+//! Cables are `/obj/structure/cable` datums, connected to the `/obj/machinery/power` datums of
+//! their tile. Two styles of cables are supported:
+//!
+//! - directional cables have two ends, read from the `d1` and `d2` vars or from an `icon_state`
+//!   such as `"0-4"`, `0` being a node connected to the machines of the tile;
+//! - bitmask cables, as in recent /tg/station, connect to the cables on the same `cable_layer` on
+//!   their tile and the four tiles around, and to the machines of their tile.
+
+use std::collections::HashMap;
+
+use super::{reverse, step, Placed, UnionFind, EAST, NORTH, SOUTH, WEST};
+use crate::{Datum, Literal, DMM};
+
+const CABLE: &str = "/obj/structure/cable";
+const POWER_MACHINE: &str = "/obj/machinery/power";
+const DEFAULT_CABLE_LAYER: i64 = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CableStyle {
+    Directional,
+    Bitmask,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowerOptions {
+    pub style: CableStyle,
+    /// Machines providing power to their network, with their subtypes
+    pub sources: Vec<String>,
+}
+
+impl Default for PowerOptions {
+    fn default() -> Self {
+        PowerOptions {
+            style: CableStyle::Directional,
+            sources: [
+                "/obj/machinery/power/smes",
+                "/obj/machinery/power/port_gen",
+                "/obj/machinery/power/rtg",
+                "/obj/machinery/power/solar",
+                "/obj/machinery/power/generator",
+                "/obj/machinery/power/turbine",
+            ]
+            .iter()
+            .map(|source| source.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// End of a directional cable that connects to nothing
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CableEnd {
+    pub coords: (u32, u32, u32),
+    pub dir: u8,
+}
+
+/// Cables connected together, with the machines they connect
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowerNetwork<'d> {
+    pub cables: Vec<Placed<'d>>,
+    /// Machines on the network, such as SMES, APC and terminals
+    pub members: Vec<Placed<'d>>,
+    /// Whether a member is a power source
+    pub powered: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PowerAnalysis<'d> {
+    /// Networks ordered by their first cable, ordered by z, y then x
+    pub networks: Vec<PowerNetwork<'d>>,
+    pub unconnected_ends: Vec<CableEnd>,
+}
+
+impl<'d> PowerAnalysis<'d> {
+    /// Networks without a power source
+    pub fn unpowered(&self) -> impl Iterator<Item = &PowerNetwork<'d>> {
+        self.networks.iter().filter(|network| !network.powered)
+    }
+}
+
+struct Cable<'d> {
+    placed: Placed<'d>,
+    dirs: [u8; 2],
+    layer: i64,
+}
+
+fn number_var(datum: &Datum, var: &str) -> Option<i64> {
+    datum.var_edit(var).and_then(Literal::as_number)
+}
+
+/// Ends of a directional cable, `0-1` by default
+fn cable_dirs(datum: &Datum) -> [u8; 2] {
+    let icon_state: Option<Vec<u8>> = datum
+        .var_edit("icon_state")
+        .and_then(Literal::as_str)
+        .and_then(|state| state.split('-').map(|dir| dir.parse().ok()).collect());
+    let (d1, d2) = match icon_state.as_deref() {
+        Some(&[d1, d2]) => (d1, d2),
+        _ => (0, 1),
+    };
+    [
+        number_var(datum, "d1").map_or(d1, |d| d as u8),
+        number_var(datum, "d2").map_or(d2, |d| d as u8),
+    ]
+}
+
+impl DMM {
+    /// Rebuild the power networks of the map
+    pub fn power_networks(&self, options: &PowerOptions) -> PowerAnalysis<'_> {
+        let mut tiles: Vec<_> = self.iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+
+        // Cables first, then machines, indexed together
+        let mut cables = Vec::new();
+        let mut machines = Vec::new();
+        for &(coords, datums) in &tiles {
+            for datum in datums {
+                let placed = Placed { coords, datum };
+                if datum.is_subtype_of(CABLE) {
+                    cables.push(Cable {
+                        placed,
+                        dirs: cable_dirs(datum),
+                        layer: number_var(datum, "cable_layer").unwrap_or(DEFAULT_CABLE_LAYER),
+                    });
+                } else if datum.is_subtype_of(POWER_MACHINE) {
+                    machines.push(placed);
+                }
+            }
+        }
+        let mut cables_at: HashMap<_, Vec<usize>> = HashMap::new();
+        for (index, cable) in cables.iter().enumerate() {
+            cables_at
+                .entry(cable.placed.coords)
+                .or_default()
+                .push(index);
+        }
+        let mut machines_at: HashMap<_, Vec<usize>> = HashMap::new();
+        for (index, machine) in machines.iter().enumerate() {
+            machines_at
+                .entry(machine.coords)
+                .or_default()
+                .push(cables.len() + index);
+        }
+        let at = |map: &HashMap<_, Vec<usize>>, coords| -> Vec<usize> {
+            map.get(&coords).cloned().unwrap_or_default()
+        };
+
+        let mut sets = UnionFind::new(cables.len() + machines.len());
+        let mut unconnected_ends = Vec::new();
+        for (index, cable) in cables.iter().enumerate() {
+            let coords = cable.placed.coords;
+            match options.style {
+                CableStyle::Directional => {
+                    for &dir in &cable.dirs {
+                        if dir == 0 {
+                            for machine in at(&machines_at, coords) {
+                                sets.union(index, machine);
+                            }
+                        } else {
+                            // Cables of the next tile ending towards this one
+                            let mut targets = vec![(step(coords, dir), reverse(dir))];
+                            if dir.count_ones() == 2 {
+                                // Diagonal cables also meet on the two tiles around the corner
+                                let vertical = dir & (NORTH | SOUTH);
+                                let horizontal = dir & (EAST | WEST);
+                                targets
+                                    .push((step(coords, vertical), reverse(vertical) | horizontal));
+                                targets.push((
+                                    step(coords, horizontal),
+                                    vertical | reverse(horizontal),
+                                ));
+                            }
+                            let mut connected = false;
+                            for (target, end) in targets {
+                                for other in target.map_or_else(Vec::new, |t| at(&cables_at, t)) {
+                                    if cables[other].dirs.contains(&end) {
+                                        sets.union(index, other);
+                                        connected = true;
+                                    }
+                                }
+                            }
+                            if !connected {
+                                unconnected_ends.push(CableEnd { coords, dir });
+                            }
+                        }
+                        // Cables of the same tile sharing an end
+                        for other in at(&cables_at, coords) {
+                            if other != index && cables[other].dirs.contains(&dir) {
+                                sets.union(index, other);
+                            }
+                        }
+                    }
+                }
+                CableStyle::Bitmask => {
+                    for machine in at(&machines_at, coords) {
+                        sets.union(index, machine);
+                    }
+                    let neighbours = [NORTH, SOUTH, EAST, WEST]
+                        .iter()
+                        .filter_map(|&dir| step(coords, dir))
+                        .chain(Some(coords));
+                    for neighbour in neighbours {
+                        for other in at(&cables_at, neighbour) {
+                            if cables[other].layer == cable.layer {
+                                sets.union(index, other);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let networks = sets
+            .groups()
+            .into_iter()
+            // Machines without cables are not a network
+            .filter(|group| group[0] < cables.len())
+            .map(|group| {
+                let (network_cables, members): (Vec<_>, Vec<_>) =
+                    group.into_iter().partition(|&i| i < cables.len());
+                let members: Vec<_> = members
+                    .into_iter()
+                    .map(|i| machines[i - cables.len()])
+                    .collect();
+                let powered = members.iter().any(|member| {
+                    options
+                        .sources
+                        .iter()
+                        .any(|source| member.datum.is_subtype_of(source))
+                });
+                PowerNetwork {
+                    cables: network_cables
+                        .into_iter()
+                        .map(|i| cables[i].placed)
+                        .collect(),
+                    members,
+                    powered,
+                }
+            })
+            .collect();
+
+        PowerAnalysis {
+            networks,
+            unconnected_ends,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    fn cable(icon_state: &str) -> Datum {
+        Datum::with_var_edits(
+            CABLE,
+            vec![(
+                "icon_state".to_string(),
+                Literal::Str(icon_state.to_string()),
+            )]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    /// Map of a single row of tiles
+    fn row(tiles: Vec<Vec<Datum>>) -> DMM {
+        let tiles: Vec<_> = (1..).map(|x| (x, 1, 1)).zip(tiles).collect();
+        test_map(&tiles)
+    }
+
+    #[test]
+    fn test_directional() {
+        // SMES - cable - APC, then a loose cable
+        let dmm = row(vec![
+            vec![Datum::new("/obj/machinery/power/smes"), cable("0-4")],
+            vec![cable("4-8")],
+            vec![
+                cable("0-8"),
+                Datum::new("/obj/machinery/power/apc"),
+                Datum::new("/obj/machinery/power/terminal"),
+            ],
+            vec![cable("1-2")],
+        ]);
+
+        let analysis = dmm.power_networks(&PowerOptions::default());
+        assert_eq!(analysis.networks.len(), 2);
+        let network = &analysis.networks[0];
+        assert_eq!(network.cables.len(), 3);
+        let members: Vec<_> = network.members.iter().map(|m| m.datum.path()).collect();
+        assert_eq!(
+            members,
+            vec![
+                "/obj/machinery/power/smes",
+                "/obj/machinery/power/apc",
+                "/obj/machinery/power/terminal"
+            ]
+        );
+        assert!(network.powered);
+        assert_eq!(analysis.unpowered().count(), 1);
+        assert_eq!(
+            analysis.unconnected_ends,
+            vec![
+                CableEnd {
+                    coords: (4, 1, 1),
+                    dir: NORTH
+                },
+                CableEnd {
+                    coords: (4, 1, 1),
+                    dir: SOUTH
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bitmask() {
+        let layer = |layer| {
+            Datum::with_var_edits(
+                CABLE,
+                vec![("cable_layer".to_string(), Literal::Number(layer))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let dmm = row(vec![
+            vec![Datum::new("/obj/machinery/power/apc"), Datum::new(CABLE)],
+            vec![layer(2)],
+            vec![layer(1)],
+            vec![layer(1)],
+        ]);
+
+        let options = PowerOptions {
+            style: CableStyle::Bitmask,
+            ..PowerOptions::default()
+        };
+        let analysis = dmm.power_networks(&options);
+        assert_eq!(analysis.networks.len(), 2);
+        assert_eq!(analysis.networks[0].cables.len(), 2);
+        assert_eq!(analysis.networks[0].members.len(), 1);
+        assert_eq!(analysis.networks[1].cables.len(), 2);
+        assert!(analysis.unconnected_ends.is_empty());
+        assert_eq!(analysis.unpowered().count(), 2);
+    }
+}
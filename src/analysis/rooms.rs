@@ -0,0 +1,187 @@
+//! Rooms and hull breaches.
+//!
+//! Tiles are either space, sealed (a wall turf, or a door or window on the tile), or open. Rooms
+//! are the groups of open tiles connected in the cardinal directions. A room next to space is
+//! breached: its air would leak without passing a door, window or wall.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{cardinal_neighbours, connected_groups};
+use crate::{Datum, DMM};
+
+/// Types deciding the kind of a tile, with their subtypes
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoomOptions {
+    pub space: Vec<String>,
+    pub walls: Vec<String>,
+    /// Objects sealing the tile they are on
+    pub barriers: Vec<String>,
+}
+
+fn strings(paths: &[&str]) -> Vec<String> {
+    paths.iter().map(|path| path.to_string()).collect()
+}
+
+impl Default for RoomOptions {
+    fn default() -> Self {
+        RoomOptions {
+            space: strings(&["/turf/open/space", "/turf/space"]),
+            walls: strings(&[
+                "/turf/closed",
+                "/turf/simulated/wall",
+                "/turf/unsimulated/wall",
+            ]),
+            barriers: strings(&[
+                "/obj/machinery/door",
+                "/obj/structure/window",
+                "/obj/effect/spawner/structure/window",
+                "/obj/structure/falsewall",
+            ]),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TileKind {
+    Space,
+    Sealed,
+    Open,
+}
+
+impl RoomOptions {
+    pub fn classify(&self, tile: &[Datum]) -> TileKind {
+        let is_any = |datum: &Datum, paths: &[String]| paths.iter().any(|p| datum.is_subtype_of(p));
+        if tile.iter().any(|d| is_any(d, &self.space)) {
+            TileKind::Space
+        } else if tile
+            .iter()
+            .any(|d| is_any(d, &self.walls) || is_any(d, &self.barriers))
+        {
+            TileKind::Sealed
+        } else {
+            TileKind::Open
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Room {
+    /// Tiles of the room, ordered by z, y then x
+    pub tiles: Vec<(u32, u32, u32)>,
+    /// Shortest path from the first tile of the room to space, ending on the space tile, when
+    /// the room is breached
+    pub breach: Option<Vec<(u32, u32, u32)>>,
+}
+
+impl Room {
+    pub fn is_breached(&self) -> bool {
+        self.breach.is_some()
+    }
+}
+
+impl DMM {
+    /// Every room of the map, ordered by their first tile
+    pub fn rooms(&self, options: &RoomOptions) -> Vec<Room> {
+        let kinds: HashMap<_, _> = self
+            .iter()
+            .map(|(coords, tile)| (coords, options.classify(tile)))
+            .collect();
+        let open: HashSet<_> = kinds
+            .iter()
+            .filter(|(_, kind)| **kind == TileKind::Open)
+            .map(|(coords, _)| *coords)
+            .collect();
+
+        connected_groups(&open)
+            .into_iter()
+            .map(|tiles| {
+                let breach = path_to_space(&tiles, &kinds);
+                Room { tiles, breach }
+            })
+            .collect()
+    }
+
+    /// Rooms open to space
+    pub fn hull_breaches(&self, options: &RoomOptions) -> Vec<Room> {
+        self.rooms(options)
+            .into_iter()
+            .filter(Room::is_breached)
+            .collect()
+    }
+}
+
+/// Breadth-first search from the first tile of the room to the closest space tile
+fn path_to_space(
+    room: &[(u32, u32, u32)],
+    kinds: &HashMap<(u32, u32, u32), TileKind>,
+) -> Option<Vec<(u32, u32, u32)>> {
+    let room_tiles: HashSet<_> = room.iter().collect();
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from(vec![room[0]]);
+    previous.insert(room[0], room[0]);
+    while let Some(tile) = queue.pop_front() {
+        for neighbour in cardinal_neighbours(tile) {
+            if previous.contains_key(&neighbour) {
+                continue;
+            }
+            if kinds.get(&neighbour) == Some(&TileKind::Space) {
+                let mut path = vec![neighbour, tile];
+                let mut current = tile;
+                while current != room[0] {
+                    current = previous[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if room_tiles.contains(&neighbour) {
+                previous.insert(neighbour, tile);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    /// Map drawn with `#` for walls, `.` for floors, `D` for doors and ` ` for space
+    fn map(rows: &[&str]) -> DMM {
+        let floor = || Datum::new("/turf/open/floor");
+        let content = |c| match c {
+            '#' => vec![Datum::new("/turf/closed/wall")],
+            '.' => vec![floor()],
+            'D' => vec![Datum::new("/obj/machinery/door/airlock"), floor()],
+            _ => vec![Datum::new("/turf/open/space")],
+        };
+        let mut tiles = Vec::new();
+        for (y, row) in (1..).zip(rows) {
+            for (x, c) in (1..).zip(row.chars()) {
+                tiles.push(((x, y, 1), content(c)));
+            }
+        }
+        test_map(&tiles)
+    }
+
+    #[test]
+    fn test_rooms() {
+        let dmm = map(&[
+            "#####  ", //
+            "#..D.. ", //
+            "#####  ",
+        ]);
+        let rooms = dmm.rooms(&RoomOptions::default());
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].tiles, vec![(2, 2, 1), (3, 2, 1)]);
+        assert!(!rooms[0].is_breached());
+        assert_eq!(
+            rooms[1].breach,
+            Some(vec![(5, 2, 1), (6, 2, 1), (6, 1, 1)]),
+            "the closest space tile from the first tile of the room"
+        );
+        assert_eq!(dmm.hull_breaches(&RoomOptions::default()).len(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+//! Tile level comparison of two maps.
+//!
+//! Maps are compared on the resolved content of their tiles, so the keys used by each map do not
+//! matter.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{Datum, Literal, DMM};
+
+/// A change of one tile
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    DatumAdded(Datum),
+    DatumRemoved(Datum),
+    /// A var edit of a datum was added, removed or changed
+    VarEditChanged {
+        path: String,
+        var: String,
+        old: Option<Literal>,
+        new: Option<Literal>,
+    },
+    TurfReplaced {
+        old: Datum,
+        new: Datum,
+    },
+    AreaChanged {
+        old: Datum,
+        new: Datum,
+    },
+}
+
+impl Change {
+    /// Types concerned by the change
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            Change::DatumAdded(datum) | Change::DatumRemoved(datum) => vec![datum.path()],
+            Change::VarEditChanged { path, .. } => vec![path],
+            Change::TurfReplaced { old, new } | Change::AreaChanged { old, new } => {
+                vec![old.path(), new.path()]
+            }
+        }
+    }
+}
+
+/// A change with the coordinates of its tile
+pub type LocatedChange<'d> = ((u32, u32, u32), &'d Change);
+
+/// Changes of one tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileDiff {
+    pub coords: (u32, u32, u32),
+    /// Area of the tile in the new map, or in the old one if it has none
+    pub area: Option<String>,
+    pub changes: Vec<Change>,
+}
+
+/// Every changed tile between two maps, ordered by z, y then x
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    pub tiles: Vec<TileDiff>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Changed tiles grouped by area
+    pub fn by_area(&self) -> BTreeMap<Option<&str>, Vec<&TileDiff>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for tile in &self.tiles {
+            groups.entry(tile.area.as_deref()).or_default().push(tile);
+        }
+        groups
+    }
+
+    /// Changes grouped by the types they concern, with the coordinates of their tile
+    pub fn by_type(&self) -> BTreeMap<&str, Vec<LocatedChange<'_>>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for tile in &self.tiles {
+            for change in &tile.changes {
+                for path in change.paths().into_iter().collect::<BTreeSet<_>>() {
+                    groups.entry(path).or_default().push((tile.coords, change));
+                }
+            }
+        }
+        groups
+    }
+}
+
+/// Compare the tiles of two maps
+pub fn diff(old: &DMM, new: &DMM) -> Diff {
+    let old_tiles: HashMap<_, _> = old.iter().collect();
+    let new_tiles: HashMap<_, _> = new.iter().collect();
+    let coords: BTreeSet<_> = old_tiles
+        .keys()
+        .chain(new_tiles.keys())
+        .map(|&(x, y, z)| (z, y, x))
+        .collect();
+
+    let tiles = coords
+        .into_iter()
+        .map(|(z, y, x)| (x, y, z))
+        .filter_map(|coords| {
+            let old_tile = old_tiles.get(&coords).copied().unwrap_or_default();
+            let new_tile = new_tiles.get(&coords).copied().unwrap_or_default();
+            if same_tile(old_tile, new_tile) {
+                return None;
+            }
+
+            let changes = diff_tile(old_tile, new_tile);
+            let area = new_tile
+                .iter()
+                .chain(old_tile)
+                .find(|d| d.is_area())
+                .map(|d| d.path().to_string());
+            Some(TileDiff {
+                coords,
+                area,
+                changes,
+            })
+        })
+        .collect();
+
+    Diff { tiles }
+}
+
+/// Changes between the content of two tiles. Reordering the datums is not a change.
+pub fn diff_tile(old: &[Datum], new: &[Datum]) -> Vec<Change> {
+    let categories = [Datum::is_area, Datum::is_turf, Datum::is_movable];
+    // Areas and turfs of different types replace each other
+    let replaceable = [true, true, false];
+
+    let mut changes = Vec::new();
+    for (category, &replaceable) in categories.iter().zip(&replaceable) {
+        let mut old: Vec<&Datum> = old.iter().filter(|d| category(d)).collect();
+        let mut new: Vec<&Datum> = new.iter().filter(|d| category(d)).collect();
+
+        // Unchanged datums
+        old.retain(|datum| match new.iter().position(|d| d == datum) {
+            Some(index) => {
+                new.remove(index);
+                false
+            }
+            None => true,
+        });
+
+        // Datums whose var edits changed
+        old.retain(
+            |datum| match new.iter().position(|d| d.path == datum.path) {
+                Some(index) => {
+                    diff_var_edits(datum, new.remove(index), &mut changes);
+                    false
+                }
+                None => true,
+            },
+        );
+
+        let replaced = if replaceable {
+            old.len().min(new.len())
+        } else {
+            0
+        };
+        for (old, new) in old.drain(..replaced).zip(new.drain(..replaced)) {
+            let (old, new) = (old.clone(), new.clone());
+            changes.push(if old.is_area() {
+                Change::AreaChanged { old, new }
+            } else {
+                Change::TurfReplaced { old, new }
+            });
+        }
+
+        changes.extend(old.into_iter().cloned().map(Change::DatumRemoved));
+        changes.extend(new.into_iter().cloned().map(Change::DatumAdded));
+    }
+    changes
+}
+
+/// Whether two tiles have the same content, in any order
+pub fn same_tile(a: &[Datum], b: &[Datum]) -> bool {
+    a == b || diff_tile(a, b).is_empty()
+}
+
+fn diff_var_edits(old: &Datum, new: &Datum, changes: &mut Vec<Change>) {
+    let vars: BTreeSet<_> = old.var_edits.keys().chain(new.var_edits.keys()).collect();
+    for var in vars {
+        let (old_value, new_value) = (old.var_edit(var), new.var_edit(var));
+        if old_value != new_value {
+            changes.push(Change::VarEditChanged {
+                path: new.path.clone(),
+                var: var.clone(),
+                old: old_value.cloned(),
+                new: new_value.cloned(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    fn light(dir: i64) -> Datum {
+        Datum::with_var_edits(
+            "/obj/machinery/light",
+            vec![("dir".to_string(), Literal::Number(dir))]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let floor = Datum::new("/turf/open/floor");
+        let bridge = Datum::new("/area/bridge");
+        let old = test_map(&[
+            ((1, 1, 1), vec![light(4), floor.clone(), bridge.clone()]),
+            ((1, 2, 1), vec![floor.clone(), bridge.clone()]),
+        ]);
+        // Same tiles with different keys
+        let new = test_map(&[
+            ((1, 2, 1), vec![floor.clone(), bridge.clone()]),
+            ((1, 1, 1), vec![light(4), floor.clone(), bridge.clone()]),
+        ]);
+        assert_ne!(old.tile_keys(), new.tile_keys());
+        assert!(diff(&old, &new).is_empty());
+
+        let wall = Datum::new("/turf/closed/wall");
+        let hallway = Datum::new("/area/hallway");
+        let new = test_map(&[
+            (
+                (1, 1, 1),
+                vec![
+                    light(8),
+                    Datum::new("/obj/item"),
+                    floor.clone(),
+                    hallway.clone(),
+                ],
+            ),
+            ((1, 2, 1), vec![wall.clone(), bridge]),
+        ]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.tiles,
+            vec![
+                TileDiff {
+                    coords: (1, 1, 1),
+                    area: Some("/area/hallway".to_string()),
+                    changes: vec![
+                        Change::AreaChanged {
+                            old: Datum::new("/area/bridge"),
+                            new: hallway,
+                        },
+                        Change::VarEditChanged {
+                            path: "/obj/machinery/light".to_string(),
+                            var: "dir".to_string(),
+                            old: Some(Literal::Number(4)),
+                            new: Some(Literal::Number(8)),
+                        },
+                        Change::DatumAdded(Datum::new("/obj/item")),
+                    ],
+                },
+                TileDiff {
+                    coords: (1, 2, 1),
+                    area: Some("/area/bridge".to_string()),
+                    changes: vec![Change::TurfReplaced {
+                        old: floor,
+                        new: wall,
+                    }],
+                },
+            ]
+        );
+        assert_eq!(diff.by_area().len(), 2);
+        let by_type = diff.by_type();
+        assert_eq!(by_type["/turf/open/floor"].len(), 1);
+        assert_eq!(by_type["/obj/machinery/light"][0].0, (1, 1, 1));
+    }
+}
@@ -0,0 +1,321 @@
+//! Bulk edition of the datums of a map.
+//!
+//! Every operation visits each dictionary entry once, edits the datums selected by a
+//! [`Predicate`], then merges the entries that became identical.
+
+use crate::{Datum, Literal, DMM};
+
+/// Selects datums. `tile` is the content of the dictionary entry containing `datum`.
+pub trait Predicate {
+    fn matches(&self, datum: &Datum, tile: &[Datum]) -> bool;
+}
+
+impl<F: Fn(&Datum, &[Datum]) -> bool> Predicate for F {
+    fn matches(&self, datum: &Datum, tile: &[Datum]) -> bool {
+        self(datum, tile)
+    }
+}
+
+/// How [`Filter`] matches the path of datums
+#[derive(Clone, Debug, PartialEq)]
+enum PathMatch {
+    Exact(String),
+    Subtypes(String),
+    Glob(String),
+}
+
+/// Predicate built from common conditions, which must all be met
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    path: Option<PathMatch>,
+    /// Var edits with their value, `None` for any value
+    vars: Vec<(String, Option<Literal>)>,
+    area: Option<String>,
+}
+
+impl Filter {
+    /// Match every datum
+    pub fn any() -> Self {
+        Filter::default()
+    }
+
+    /// Match the datums of type `path`
+    pub fn path<S: Into<String>>(path: S) -> Self {
+        Filter {
+            path: Some(PathMatch::Exact(path.into())),
+            ..Filter::default()
+        }
+    }
+
+    /// Match the datums of type `path` or one of its subtypes
+    pub fn subtypes_of<S: Into<String>>(path: S) -> Self {
+        Filter {
+            path: Some(PathMatch::Subtypes(path.into())),
+            ..Filter::default()
+        }
+    }
+
+    /// Match the datums whose type matches `pattern`, where `*` matches any characters and `?`
+    /// any single character, such as `/obj/machinery/door/*/command`
+    pub fn glob<S: Into<String>>(pattern: S) -> Self {
+        Filter {
+            path: Some(PathMatch::Glob(pattern.into())),
+            ..Filter::default()
+        }
+    }
+
+    /// Only match datums whose `var` is edited to `value`
+    pub fn with_var<S: Into<String>>(mut self, var: S, value: Literal) -> Self {
+        self.vars.push((var.into(), Some(value)));
+        self
+    }
+
+    /// Only match datums whose `var` is edited, to any value
+    pub fn has_var<S: Into<String>>(mut self, var: S) -> Self {
+        self.vars.push((var.into(), None));
+        self
+    }
+
+    /// Only match datums on a tile of `area` or one of its subtypes
+    pub fn in_area<S: Into<String>>(mut self, area: S) -> Self {
+        self.area = Some(area.into());
+        self
+    }
+}
+
+impl Predicate for Filter {
+    fn matches(&self, datum: &Datum, tile: &[Datum]) -> bool {
+        let path_matches = match &self.path {
+            Some(PathMatch::Exact(path)) => datum.path == *path,
+            Some(PathMatch::Subtypes(path)) => datum.is_subtype_of(path),
+            Some(PathMatch::Glob(pattern)) => {
+                glob_matches(pattern.as_bytes(), datum.path.as_bytes())
+            }
+            None => true,
+        };
+        let area_matches = match &self.area {
+            Some(area) => tile.iter().any(|d| d.is_area() && d.is_subtype_of(area)),
+            None => true,
+        };
+
+        path_matches
+            && area_matches
+            && self.vars.iter().all(|(var, value)| match value {
+                Some(value) => datum.var_edit(var) == Some(value),
+                None => datum.var_edit(var).is_some(),
+            })
+    }
+}
+
+/// Whether `path` matches `pattern`, where `*` matches any sequence and `?` any character.
+///
+/// On a mismatch, only the last `*` takes one more character: the earlier ones never need to,
+/// so matching does not backtrack further.
+fn glob_matches(pattern: &[u8], path: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // Pattern position after the last `*`, and the path position where its match ends
+    let mut star = None;
+    while s < path.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, s));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == path[s] => {
+                p += 1;
+                s += 1;
+            }
+            _ => match star {
+                Some((after_star, end)) => {
+                    star = Some((after_star, end + 1));
+                    p = after_star;
+                    s = end + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+impl DMM {
+    /// Call `edit` on every datum matching `predicate`.
+    ///
+    /// Returns the number of datum instances placed on the map that were changed.
+    pub fn edit_datums<P, E>(&mut self, predicate: &P, mut edit: E) -> usize
+    where
+        P: Predicate + ?Sized,
+        E: FnMut(&mut Datum),
+    {
+        let uses = self.key_uses();
+        let mut changed = 0;
+        for (key, datums) in &mut self.dictionary {
+            let tile = datums.clone();
+            for datum in datums.iter_mut() {
+                if predicate.matches(datum, &tile) {
+                    let old = datum.clone();
+                    edit(datum);
+                    if *datum != old {
+                        changed += uses.get(key).copied().unwrap_or_default();
+                    }
+                }
+            }
+        }
+        self.merge_duplicate_entries();
+        changed
+    }
+
+    /// Set `var` to `value` on the matching datums
+    pub fn set_var<P: Predicate + ?Sized>(
+        &mut self,
+        predicate: &P,
+        var: &str,
+        value: Literal,
+    ) -> usize {
+        self.edit_datums(predicate, |datum| {
+            datum.var_edits.insert(var.to_string(), value.clone());
+        })
+    }
+
+    /// Rename the var edit `from` to `to` on the matching datums. Datums where `to` is already
+    /// edited are left unchanged.
+    pub fn rename_var<P: Predicate + ?Sized>(
+        &mut self,
+        predicate: &P,
+        from: &str,
+        to: &str,
+    ) -> usize {
+        self.edit_datums(predicate, |datum| {
+            if datum.var_edits.contains_key(to) {
+                return;
+            }
+            if let Some(value) = datum.var_edits.remove(from) {
+                datum.var_edits.insert(to.to_string(), value);
+            }
+        })
+    }
+
+    /// Remove the var edit `var` from the matching datums
+    pub fn remove_var<P: Predicate + ?Sized>(&mut self, predicate: &P, var: &str) -> usize {
+        self.edit_datums(predicate, |datum| {
+            datum.var_edits.remove(var);
+        })
+    }
+
+    /// Replace the value of `var` on the matching datums by the result of `transform`.
+    ///
+    /// `transform` gets the current value, if any, and returns the new one, or `None` to remove
+    /// the var edit.
+    pub fn transform_var<P, T>(&mut self, predicate: &P, var: &str, mut transform: T) -> usize
+    where
+        P: Predicate + ?Sized,
+        T: FnMut(Option<&Literal>) -> Option<Literal>,
+    {
+        self.edit_datums(predicate, |datum| {
+            match transform(datum.var_edits.get(var)) {
+                Some(value) => datum.var_edits.insert(var.to_string(), value),
+                None => datum.var_edits.remove(var),
+            };
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Key};
+
+    fn door(access: i64) -> Datum {
+        Datum::with_var_edits(
+            "/obj/machinery/door/airlock/command",
+            vec![("req_access".to_string(), Literal::Number(access))]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    fn station() -> DMM {
+        test_map(&[
+            ((1, 1, 1), [door(19), Datum::new("/area/bridge")]),
+            ((1, 2, 1), [door(20), Datum::new("/area/bridge")]),
+            ((1, 3, 1), [door(19), Datum::new("/area/hallway")]),
+        ])
+    }
+
+    #[test]
+    fn test_set_var_merges_entries() {
+        let mut dmm = station();
+        let filter = Filter::subtypes_of("/obj/machinery/door").in_area("/area/bridge");
+
+        assert_eq!(dmm.set_var(&filter, "req_access", Literal::Number(19)), 1);
+        assert_eq!(dmm.dictionary().len(), 2);
+        assert_eq!(
+            dmm.grid()[&(1, 1, 1)],
+            vec![Key::new(0), Key::new(0), Key::new(2)]
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let tile = [door(19), Datum::new("/area/bridge")];
+        let glob = Filter::glob("/obj/machinery/door/*/command");
+        assert!(glob.matches(&tile[0], &tile));
+        assert!(!Filter::glob("/obj/*/door").matches(&tile[0], &tile));
+        assert!(Filter::glob("/obj/*/door/?irlock*").matches(&tile[0], &tile));
+
+        assert!(glob.clone().has_var("req_access").matches(&tile[0], &tile));
+        assert!(!glob.has_var("name").matches(&tile[0], &tile));
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches(b"", b""));
+        assert!(glob_matches(b"*", b""));
+        assert!(glob_matches(b"/obj/**", b"/obj/item"));
+        assert!(glob_matches(b"*/item/*n", b"/obj/item/pen"));
+        assert!(!glob_matches(b"*/item/*n", b"/obj/item/pens"));
+        assert!(!glob_matches(b"/obj/?", b"/obj/"));
+
+        // Would take exponential time with backtracking on every `*`
+        let path = [b'a'; 64];
+        assert!(!glob_matches(b"*a*a*a*a*a*a*a*a*a*a*a*a*b", &path));
+    }
+
+    #[test]
+    fn test_var_operations() {
+        let mut dmm = station();
+        let hallway = Filter::any().in_area("/area/hallway");
+
+        // An edited var is not overwritten
+        let mut both = station();
+        both.set_var(&hallway, "req_one_access", Literal::Number(20));
+        assert_eq!(both.rename_var(&hallway, "req_access", "req_one_access"), 0);
+        assert_eq!(
+            both.dictionary()[&Key::new(2)][0].var_edit("req_one_access"),
+            Some(&Literal::Number(20))
+        );
+
+        assert_eq!(dmm.rename_var(&hallway, "req_access", "req_one_access"), 1);
+        assert_eq!(
+            dmm.dictionary()[&Key::new(2)][0].var_edit("req_one_access"),
+            Some(&Literal::Number(19))
+        );
+
+        let filter = Filter::path("/obj/machinery/door/airlock/command")
+            .with_var("req_access", Literal::Number(20));
+        assert_eq!(dmm.remove_var(&filter, "req_access"), 1);
+        assert!(dmm.dictionary()[&Key::new(1)][0].var_edits().is_empty());
+
+        let doubled = dmm.transform_var(
+            &|d: &Datum, _: &[Datum]| d.var_edit("req_access").is_some(),
+            "req_access",
+            |value| {
+                value
+                    .and_then(Literal::as_number)
+                    .map(|n| Literal::Number(2 * n))
+            },
+        );
+        assert_eq!(doubled, 1);
+        assert_eq!(dmm.dictionary()[&Key::new(0)][0], door(38));
+    }
+}
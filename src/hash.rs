@@ -0,0 +1,182 @@
+//! Stable content hashes.
+//!
+//! Hashes are computed from the resolved content of the tiles with 64-bit FNV-1a, so they do
+//! not depend on the keys, the layout of the file or the platform, and stay the same between
+//! versions of this crate.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Datum, Literal, DMM};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    /// Write a string with its length, so that consecutive strings cannot be confused
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Path(p) => {
+                self.write(&[0]);
+                self.write_str(p);
+            }
+            Literal::Str(s) => {
+                self.write(&[1]);
+                self.write_str(s);
+            }
+            Literal::Number(n) => {
+                self.write(&[2]);
+                self.write(&n.to_le_bytes());
+            }
+            Literal::Float(f) => {
+                self.write(&[3]);
+                self.write_u64(f.to_bits());
+            }
+            Literal::Null => self.write(&[4]),
+            Literal::List(list) => {
+                self.write(&[5]);
+                self.write_u64(list.len() as u64);
+                for item in list {
+                    self.write_literal(item);
+                }
+            }
+        }
+    }
+
+    fn write_datum(&mut self, datum: &Datum) {
+        self.write_str(&datum.path);
+        let mut vars: Vec<_> = datum.var_edits.iter().collect();
+        vars.sort_unstable_by_key(|(var, _)| *var);
+        self.write_u64(vars.len() as u64);
+        for (var, value) in vars {
+            self.write_str(var);
+            self.write_literal(value);
+        }
+    }
+}
+
+/// Hash of the content of a tile. The order of the datums matters, the order of their var edits
+/// does not.
+pub fn tile_hash(datums: &[Datum]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write_u64(datums.len() as u64);
+    for datum in datums {
+        hasher.write_datum(datum);
+    }
+    hasher.0
+}
+
+impl DMM {
+    /// Hash of every tile
+    pub fn tile_hashes(&self) -> HashMap<(u32, u32, u32), u64> {
+        self.iter()
+            .map(|(coords, datums)| (coords, tile_hash(datums)))
+            .collect()
+    }
+
+    /// Hash of each z-level, from the hashes and coordinates of its tiles
+    pub fn z_level_hashes(&self) -> BTreeMap<u32, u64> {
+        let mut tiles: Vec<_> = self.tile_hashes().into_iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+
+        let mut hashers: BTreeMap<u32, Fnv1a> = BTreeMap::new();
+        for ((x, y, z), hash) in tiles {
+            let hasher = hashers.entry(z).or_insert_with(Fnv1a::new);
+            hasher.write_u32(x);
+            hasher.write_u32(y);
+            hasher.write_u64(hash);
+        }
+        hashers
+            .into_iter()
+            .map(|(z, hasher)| (z, hasher.0))
+            .collect()
+    }
+
+    /// Hash of the whole map, from the hashes of its z-levels
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        for (z, hash) in self.z_level_hashes() {
+            hasher.write_u32(z);
+            hasher.write_u64(hash);
+        }
+        hasher.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    fn tiles(dir: i64) -> Vec<((u32, u32, u32), Vec<Datum>)> {
+        let light = Datum::with_var_edits(
+            "/obj/machinery/light",
+            vec![
+                ("dir".to_string(), Literal::Number(dir)),
+                ("name".to_string(), Literal::Str("light".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let floor = Datum::new("/turf/open/floor");
+        vec![
+            ((1, 1, 1), vec![floor.clone()]),
+            ((1, 2, 1), vec![light, floor.clone()]),
+            ((1, 1, 2), vec![floor]),
+        ]
+    }
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(tile_hash(&[]), 0xa8c7_f832_281a_39c5);
+        assert_ne!(
+            tile_hash(&[Datum::new("/turf/a"), Datum::new("/turf/b")]),
+            tile_hash(&[Datum::new("/turf/b"), Datum::new("/turf/a")])
+        );
+
+        // Independent of the keys
+        let dmm = test_map(&tiles(4));
+        let mut light_first = tiles(4);
+        light_first.rotate_left(1);
+        let other_keys = test_map(&light_first);
+        assert_ne!(dmm.tile_keys(), other_keys.tile_keys());
+        assert_eq!(dmm.fingerprint(), other_keys.fingerprint());
+        assert_eq!(dmm.tile_hashes(), other_keys.tile_hashes());
+
+        let edited = test_map(&tiles(8));
+        assert_ne!(dmm.fingerprint(), edited.fingerprint());
+        assert_ne!(dmm.z_level_hashes()[&1], edited.z_level_hashes()[&1]);
+        assert_eq!(dmm.z_level_hashes()[&2], edited.z_level_hashes()[&2]);
+
+        // The same z-level has the same hash wherever it is
+        assert_eq!(
+            dmm.z_level_hashes()[&2],
+            dmm.z_level(2).unwrap().z_level_hashes()[&1]
+        );
+    }
+}
@@ -1,8 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub mod analysis;
+pub mod diff;
+pub mod edit;
+mod hash;
+pub mod lint;
+pub mod merge;
+pub mod patch;
+mod query;
+pub mod stats;
+mod stitch;
+pub mod suggest;
+pub mod update_paths;
+pub mod validate;
+mod z_level;
+
+pub use diff::diff;
+pub use hash::tile_hash;
+pub use merge::merge;
+pub use patch::Patch;
+pub use query::Found;
+pub use stitch::{stitch, Axis, Placement};
+pub use z_level::ZLevelError;
+
 // TODO: Rework this
 struct GridIterator<'d> {
     dmm: &'d DMM,
@@ -68,6 +91,347 @@ impl DMM {
     pub fn iter(&self) -> impl Iterator<Item = ((u32, u32, u32), &[Datum])> {
         GridIterator::new(self)
     }
+
+    pub fn dictionary(&self) -> &HashMap<Key, Vec<Datum>> {
+        &self.dictionary
+    }
+
+    pub fn grid(&self) -> &HashMap<(u32, u32, u32), Vec<Key>> {
+        &self.grid
+    }
+
+    /// Content of the tile at `coords`
+    pub fn tile(&self, coords: (u32, u32, u32)) -> Option<&[Datum]> {
+        let key = self.tile_key(coords)?;
+        self.dictionary.get(&key).map(Vec::as_slice)
+    }
+
+    /// Replace the content of the tile at `coords`, adding a dictionary entry if needed.
+    ///
+    /// The entry previously used by the tile is kept, see [`DMM::prune_dictionary`].
+    pub fn set_tile(&mut self, coords: (u32, u32, u32), datums: &[Datum]) {
+        let key = self.key_for(datums);
+        self.set_tile_key(coords, key);
+    }
+
+    pub(crate) fn tile_key(&self, (x, y, z): (u32, u32, u32)) -> Option<Key> {
+        self.grid.iter().find_map(|(&(cx, cy, cz), keys)| {
+            if (cx, cz) == (x, z) && y >= cy {
+                keys.get((y - cy) as usize).copied()
+            } else {
+                None
+            }
+        })
+    }
+
+    pub(crate) fn set_tile_key(&mut self, (x, y, z): (u32, u32, u32), key: Key) {
+        let existing = self.grid.iter_mut().find_map(|(&(cx, cy, cz), keys)| {
+            if (cx, cz) == (x, z) && y >= cy {
+                keys.get_mut((y - cy) as usize)
+            } else {
+                None
+            }
+        });
+        match existing {
+            Some(k) => *k = key,
+            None => {
+                self.grid.insert((x, y, z), vec![key]);
+            }
+        }
+    }
+
+    /// Size of the map, as its largest coordinates on each axis
+    pub fn size(&self) -> (u32, u32, u32) {
+        self.grid
+            .iter()
+            .fold((0, 0, 0), |size, (&(x, y, z), keys)| {
+                let top = y + keys.len().saturating_sub(1) as u32;
+                (size.0.max(x), size.1.max(top), size.2.max(z))
+            })
+    }
+
+    /// Key of every tile of the grid
+    pub(crate) fn tile_keys(&self) -> HashMap<(u32, u32, u32), Key> {
+        self.grid
+            .iter()
+            .flat_map(|(&(x, y, z), keys)| {
+                keys.iter()
+                    .enumerate()
+                    .map(move |(i, key)| ((x, y + i as u32, z), *key))
+            })
+            .collect()
+    }
+
+    /// Replace the grid by `tiles`, stored as columns of consecutive tiles
+    pub(crate) fn set_tile_keys(&mut self, tiles: HashMap<(u32, u32, u32), Key>) {
+        let mut tiles: Vec<_> = tiles.into_iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, x, y));
+
+        self.grid.clear();
+        let mut column: Option<((u32, u32, u32), Vec<Key>)> = None;
+        for ((x, y, z), key) in tiles {
+            match &mut column {
+                Some(((cx, cy, cz), keys))
+                    if (*cx, *cz) == (x, z) && *cy + keys.len() as u32 == y =>
+                {
+                    keys.push(key)
+                }
+                _ => {
+                    if let Some((coords, keys)) = column.replace(((x, y, z), vec![key])) {
+                        self.grid.insert(coords, keys);
+                    }
+                }
+            }
+        }
+        if let Some((coords, keys)) = column {
+            self.grid.insert(coords, keys);
+        }
+    }
+
+    /// Key of the dictionary entry `datums`, added if it does not exist yet
+    pub(crate) fn key_for(&mut self, datums: &[Datum]) -> Key {
+        self.find_key(datums).unwrap_or_else(|| {
+            let key = self.next_free_key();
+            self.dictionary.insert(key, datums.to_vec());
+            key
+        })
+    }
+
+    /// Keys referenced by the grid, in ascending order
+    pub fn used_keys(&self) -> BTreeSet<Key> {
+        self.grid.values().flatten().copied().collect()
+    }
+
+    /// Number of times each key is used by the grid
+    pub(crate) fn key_uses(&self) -> HashMap<Key, usize> {
+        let mut uses = HashMap::new();
+        for key in self.grid.values().flatten() {
+            *uses.entry(*key).or_default() += 1;
+        }
+        uses
+    }
+
+    /// Remove the dictionary entries that are not referenced by the grid
+    pub fn prune_dictionary(&mut self) {
+        let used = self.used_keys();
+        self.dictionary.retain(|key, _| used.contains(key));
+    }
+
+    /// Merge the dictionary entries with identical content into the one with the smallest key.
+    ///
+    /// Returns the number of removed entries.
+    pub fn merge_duplicate_entries(&mut self) -> usize {
+        let mut keys: Vec<Key> = self.dictionary.keys().copied().collect();
+        keys.sort_unstable();
+
+        // Entries are only compared to those with the same paths
+        let mut kept: HashMap<Vec<&str>, Vec<Key>> = HashMap::new();
+        let mut replaced = HashMap::new();
+        for key in keys {
+            let datums = &self.dictionary[&key];
+            let candidates = kept
+                .entry(datums.iter().map(Datum::path).collect())
+                .or_default();
+            match candidates.iter().find(|k| self.dictionary[*k] == *datums) {
+                Some(&existing) => {
+                    replaced.insert(key, existing);
+                }
+                None => candidates.push(key),
+            }
+        }
+
+        for key in replaced.keys() {
+            self.dictionary.remove(key);
+        }
+        for key in self.grid.values_mut().flatten() {
+            if let Some(new_key) = replaced.get(key) {
+                *key = *new_key;
+            }
+        }
+        replaced.len()
+    }
+
+    /// Put the map in its canonical form: the datums of each entry are ordered movables first,
+    /// then turfs, then areas, identical entries are merged, unused ones removed, and the grid
+    /// is stored as columns of consecutive tiles.
+    ///
+    /// Keys are then given in the order the tiles are written, columns ordered by z, y then x,
+    /// so that maps with the same tiles have the same canonical form whatever their keys.
+    pub fn canonicalize(&mut self) {
+        for datums in self.dictionary.values_mut() {
+            datums.sort_by_key(|datum| {
+                if datum.is_area() {
+                    2
+                } else if datum.is_turf() {
+                    1
+                } else {
+                    0
+                }
+            });
+        }
+        self.merge_duplicate_entries();
+        let tiles = self.tile_keys();
+        self.set_tile_keys(tiles);
+
+        let mut columns: Vec<_> = self.grid.iter_mut().collect();
+        columns.sort_unstable_by_key(|(&(x, y, z), _)| (z, y, x));
+        let mut renamed = HashMap::new();
+        for key in columns.into_iter().flat_map(|(_, keys)| keys.iter_mut()) {
+            let next = Key(renamed.len() as u32);
+            *key = *renamed.entry(*key).or_insert(next);
+        }
+        let mut dictionary = std::mem::take(&mut self.dictionary);
+        self.dictionary = renamed
+            .into_iter()
+            .filter_map(|(old, new)| Some((new, dictionary.remove(&old)?)))
+            .collect();
+    }
+
+    /// Smallest key that has no dictionary entry
+    pub(crate) fn next_free_key(&self) -> Key {
+        (0..)
+            .map(Key)
+            .find(|key| !self.dictionary.contains_key(key))
+            .unwrap()
+    }
+
+    /// Smallest key whose dictionary entry is exactly `datums`
+    pub(crate) fn find_key(&self, datums: &[Datum]) -> Option<Key> {
+        self.dictionary
+            .iter()
+            .filter(|(_, d)| d.as_slice() == datums)
+            .map(|(key, _)| *key)
+            .min()
+    }
+
+    /// Change the keys so that the entries found in `reference`, such as the dictionary of the
+    /// map this one was read from, keep their key there.
+    ///
+    /// The other entries take the smallest keys left free, so that writing the map back changes
+    /// only the entries and grid lines that really changed.
+    pub fn preserve_keys(&mut self, reference: &HashMap<Key, Vec<Datum>>) {
+        // Entries are only compared to those with the same paths
+        let mut reference_entries: HashMap<Vec<&str>, Vec<(Key, &[Datum])>> = HashMap::new();
+        for (key, datums) in reference {
+            reference_entries
+                .entry(datums.iter().map(Datum::path).collect())
+                .or_default()
+                .push((*key, datums));
+        }
+        for entries in reference_entries.values_mut() {
+            entries.sort_unstable_by_key(|(key, _)| *key);
+        }
+
+        let mut keys: Vec<Key> = self.dictionary.keys().copied().collect();
+        keys.sort_unstable();
+        let mut mapping = HashMap::new();
+        let mut new_entries = Vec::new();
+        for key in keys {
+            let datums = self.dictionary[&key].as_slice();
+            let paths: Vec<&str> = datums.iter().map(Datum::path).collect();
+            let reference_key = reference_entries.get_mut(&paths).and_then(|entries| {
+                let index = entries.iter().position(|(_, d)| *d == datums)?;
+                Some(entries.remove(index).0)
+            });
+            match reference_key {
+                Some(reference_key) => {
+                    mapping.insert(key, reference_key);
+                }
+                None => new_entries.push(key),
+            }
+        }
+
+        let taken: BTreeSet<Key> = mapping.values().copied().collect();
+        let mut free = (0..).map(Key).filter(|key| !taken.contains(key));
+        for key in new_entries {
+            mapping.insert(key, free.next().unwrap());
+        }
+
+        self.dictionary = self
+            .dictionary
+            .drain()
+            .map(|(key, datums)| (mapping[&key], datums))
+            .collect();
+        for key in self.grid.values_mut().flatten() {
+            if let Some(new_key) = mapping.get(key) {
+                *key = *new_key;
+            }
+        }
+    }
+
+    /// Copy the dictionary entries of `other` named by `keys` into this map.
+    ///
+    /// An entry whose content already exists here reuses the existing key, and an entry whose
+    /// key is free keeps it. Only conflicting entries get a new key.
+    /// Returns the key of each imported entry in this map.
+    pub(crate) fn import_keys(&mut self, other: &DMM, keys: &BTreeSet<Key>) -> HashMap<Key, Key> {
+        let mut index = self.entry_index();
+        self.import_keys_with(&mut index, other, keys)
+    }
+
+    /// Index of the dictionary entries of this map, to import entries from several maps
+    pub(crate) fn entry_index(&self) -> EntryIndex {
+        let mut by_hash: HashMap<u64, Vec<Key>> = HashMap::new();
+        for (key, datums) in &self.dictionary {
+            by_hash.entry(tile_hash(datums)).or_default().push(*key);
+        }
+        EntryIndex {
+            by_hash,
+            next_free: Key(0),
+        }
+    }
+
+    /// Like [`DMM::import_keys`], with `index` made by [`DMM::entry_index`] and kept up to date
+    /// between calls
+    pub(crate) fn import_keys_with(
+        &mut self,
+        index: &mut EntryIndex,
+        other: &DMM,
+        keys: &BTreeSet<Key>,
+    ) -> HashMap<Key, Key> {
+        let mut mapping = HashMap::new();
+        for &key in keys {
+            let datums = match other.dictionary.get(&key) {
+                Some(datums) => datums,
+                None => {
+                    mapping.insert(key, key);
+                    continue;
+                }
+            };
+            let hash = tile_hash(datums);
+            let existing = index.by_hash.get(&hash).and_then(|same_hash| {
+                same_hash
+                    .iter()
+                    .filter(|k| self.dictionary[*k] == *datums)
+                    .min()
+                    .copied()
+            });
+            let new_key = match existing {
+                Some(existing) => existing,
+                None if self.dictionary.contains_key(&key) => {
+                    // Keys are only added, so the keys below the last free one stay taken
+                    while self.dictionary.contains_key(&index.next_free) {
+                        index.next_free.0 += 1;
+                    }
+                    index.next_free
+                }
+                None => key,
+            };
+            if existing.is_none() {
+                self.dictionary.insert(new_key, datums.clone());
+                index.by_hash.entry(hash).or_default().push(new_key);
+            }
+            mapping.insert(key, new_key);
+        }
+        mapping
+    }
+}
+
+/// Dictionary entries of a map by content hash, see [`DMM::entry_index`]
+pub(crate) struct EntryIndex {
+    by_hash: HashMap<u64, Vec<Key>>,
+    /// Every key below it is taken
+    next_free: Key,
 }
 
 /// In a DMM, a Datum is represented by its path (type) and a list of assigns to its var.
@@ -110,6 +474,26 @@ impl Datum {
     pub fn var_edit(&self, var: &str) -> Option<&Literal> {
         self.var_edits.get(var)
     }
+
+    pub fn is_area(&self) -> bool {
+        self.is_subtype_of("/area")
+    }
+
+    pub fn is_turf(&self) -> bool {
+        self.is_subtype_of("/turf")
+    }
+
+    /// Whether the datum is neither an area nor a turf
+    pub fn is_movable(&self) -> bool {
+        !self.is_area() && !self.is_turf()
+    }
+
+    /// Whether the datum is of type `path` or one of its subtypes
+    pub fn is_subtype_of(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.path.starts_with(path)
+            && (self.path.len() == path.len() || self.path[path.len()..].starts_with('/'))
+    }
 }
 
 /// DMM Literal
@@ -120,20 +504,21 @@ pub enum Literal {
     Str(String),
     Number(i64),
     Float(f64),
+    Null,
+    List(Vec<Literal>),
 }
 
 impl Literal {
-
     pub fn as_path(&self) -> Option<&str> {
         match self {
-            Literal::Path(p) => Some(&p),
+            Literal::Path(p) => Some(p),
             _ => None,
         }
     }
 
     pub fn as_str(&self) -> Option<&str> {
         match self {
-            Literal::Str(s) => Some(&s),
+            Literal::Str(s) => Some(s),
             _ => None,
         }
     }
@@ -151,6 +536,17 @@ impl Literal {
             _ => None,
         }
     }
+
+    pub fn as_list(&self) -> Option<&[Literal]> {
+        match self {
+            Literal::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Literal::Null
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -163,6 +559,35 @@ impl Key {
     pub fn new(k: u32) -> Self {
         Key(k)
     }
+
+    /// Number of characters needed to write the key
+    pub fn width(self) -> usize {
+        let mut width = 1;
+        let mut num = self.0 / Self::BASE.len() as u32;
+        while num != 0 {
+            width += 1;
+            num /= Self::BASE.len() as u32;
+        }
+        width
+    }
+
+    /// Write the key as found in files, padded to `width` characters
+    pub fn to_str_with_width(self, width: usize) -> String {
+        let mut result = String::new();
+        let mut num = self.0;
+
+        while num != 0 {
+            let index = num as usize % Self::BASE.len();
+            result.insert_str(0, Self::BASE.get(index..=index).unwrap());
+            num /= Self::BASE.len() as u32;
+        }
+
+        while result.len() < width {
+            result.insert(0, 'a');
+        }
+
+        result
+    }
 }
 
 impl From<u32> for Key {
@@ -230,20 +655,7 @@ mod serde_impls {
         const BASE: &'static str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
         pub fn key_to_str(key: Key) -> String {
-            let mut result = String::new();
-            let mut num = key.0;
-
-            while num != 0 {
-                let index = num as usize % Self::BASE.len();
-                result.insert_str(0, Self::BASE.get(index..=index).unwrap());
-                num /= Self::BASE.len() as u32;
-            }
-
-            while result.len() < Self::MAX_KEY_CHAR {
-                result.insert(0, 'a');
-            }
-
-            result
+            key.to_str_with_width(Self::MAX_KEY_CHAR)
         }
     }
 
@@ -254,8 +666,8 @@ mod serde_impls {
             write!(
                 formatter,
                 "a number between {} and {}, or a string of maximum {} characters within {}",
-                std::u32::MIN,
-                std::u32::MAX,
+                u32::MIN,
+                u32::MAX,
                 Self::MAX_KEY_CHAR,
                 Self::BASE
             )
@@ -283,7 +695,7 @@ mod serde_impls {
         where
             E: Error,
         {
-            if v <= u64::from(std::u32::MAX) {
+            if v <= u64::from(u32::MAX) {
                 self.visit_u32(v as u32)
             } else {
                 Err(E::invalid_value(Unexpected::Unsigned(v), &self))
@@ -332,6 +744,19 @@ mod serde_impls {
     }
 }
 
+/// Map made of `tiles`, for tests. Tiles with the same content share a dictionary entry, and
+/// entries get keys from `Key(0)` in the order their first tile is listed.
+#[cfg(test)]
+pub(crate) fn test_map<T: AsRef<[Datum]>>(tiles: &[((u32, u32, u32), T)]) -> DMM {
+    let mut dmm = DMM::new(HashMap::new(), HashMap::new());
+    for (coords, datums) in tiles {
+        dmm.set_tile(*coords, datums.as_ref());
+    }
+    let tiles = dmm.tile_keys();
+    dmm.set_tile_keys(tiles);
+    dmm
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -369,7 +794,7 @@ mod tests {
                 m
             },
         );
-        let mut iterator = dmm.iter();
+        let iterator = dmm.iter();
 
         assert_eq!(
             iterator.collect::<HashMap<(u32, u32, u32), &[Datum]>>(),
@@ -405,4 +830,34 @@ mod tests {
             .collect()
         )
     }
+
+    #[test]
+    fn test_canonicalize_keys() {
+        let tiles = [
+            (
+                (1, 1, 1),
+                vec![Datum::new("/turf/a"), Datum::new("/area/a")],
+            ),
+            (
+                (2, 1, 1),
+                vec![Datum::new("/area/a"), Datum::new("/turf/b")],
+            ),
+            (
+                (1, 2, 1),
+                vec![Datum::new("/turf/b"), Datum::new("/area/a")],
+            ),
+        ];
+        let mut dmm = test_map(&tiles);
+        let mut rotated = tiles.clone();
+        rotated.rotate_left(1);
+        let mut other = test_map(&rotated);
+        assert_ne!(dmm, other);
+
+        dmm.canonicalize();
+        other.canonicalize();
+        assert_eq!(dmm, other);
+        assert_eq!(dmm.dictionary().len(), 2);
+        assert_eq!(dmm.tile_key((1, 1, 1)), Some(Key(0)));
+        assert_eq!(dmm.tile_key((2, 1, 1)), Some(Key(1)));
+    }
 }
@@ -0,0 +1,247 @@
+//! Rule-based linting of maps.
+//!
+//! A [`Linter`] runs its [`Rule`]s on every tile of a map. Rules see the content of the tile and
+//! of the tiles around it, and report findings that become [`Diagnostic`]s with the name and
+//! severity of the rule. Rules about the map as a whole, such as its dictionary, check it once
+//! with [`Rule::check_map`].
+
+pub mod rules;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::analysis::step;
+use crate::{Datum, DMM};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// Name of the rule reporting it
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// Tile of the finding, `None` when it is not about a placed tile
+    pub coords: Option<(u32, u32, u32)>,
+    pub message: String,
+}
+
+/// Content of every tile of the map being linted
+type Tiles<'d> = HashMap<(u32, u32, u32), &'d [Datum]>;
+
+/// A tile being checked, with access to its neighbours
+#[derive(Clone, Copy, Debug)]
+pub struct Tile<'d> {
+    pub coords: (u32, u32, u32),
+    pub datums: &'d [Datum],
+    tiles: &'d Tiles<'d>,
+}
+
+impl<'d> Tile<'d> {
+    /// Content of the tile next to this one in `dir`, diagonals included
+    pub fn neighbour(&self, dir: u8) -> Option<&'d [Datum]> {
+        step(self.coords, dir).and_then(|coords| self.tiles.get(&coords).copied())
+    }
+
+    pub fn turfs(&self) -> impl Iterator<Item = &'d Datum> {
+        self.datums.iter().filter(|d| d.is_turf())
+    }
+
+    pub fn areas(&self) -> impl Iterator<Item = &'d Datum> {
+        self.datums.iter().filter(|d| d.is_area())
+    }
+}
+
+pub trait Rule {
+    /// Name of the rule, in kebab case
+    fn name(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Check `tile`, pushing a message for each finding
+    fn check(&self, _tile: &Tile<'_>, _findings: &mut Vec<String>) {}
+
+    /// Check the whole map once, pushing each finding with its tile if it has one
+    fn check_map(&self, _dmm: &DMM, _findings: &mut Vec<MapFinding>) {}
+}
+
+/// Finding of [`Rule::check_map`]: the tile it is about, if any, and its message
+pub type MapFinding = (Option<(u32, u32, u32)>, String);
+
+/// Set of rules with their severity
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Severity)>,
+}
+
+impl Default for Linter {
+    /// Same as [`Linter::new`]
+    fn default() -> Self {
+        Linter::new()
+    }
+}
+
+impl Linter {
+    /// Linter with the [built-in rules](rules::builtin)
+    pub fn new() -> Self {
+        let mut linter = Linter::empty();
+        for rule in rules::builtin() {
+            let severity = rule.default_severity();
+            linter.rules.push((rule, severity));
+        }
+        linter
+    }
+
+    /// Linter without rules, to run only the ones added to it
+    pub fn empty() -> Self {
+        Linter { rules: Vec::new() }
+    }
+
+    /// Add `rule` with its default severity
+    pub fn add<R: Rule + 'static>(&mut self, rule: R) -> &mut Self {
+        let severity = rule.default_severity();
+        self.rules.push((Box::new(rule), severity));
+        self
+    }
+
+    /// Change the severity of the rule named `name`. Returns whether the linter has it.
+    pub fn set_severity(&mut self, name: &str, severity: Severity) -> bool {
+        let mut found = false;
+        for (rule, rule_severity) in &mut self.rules {
+            if rule.name() == name {
+                *rule_severity = severity;
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Remove the rule named `name`. Returns whether the linter had it.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.rules.len();
+        self.rules.retain(|(rule, _)| rule.name() != name);
+        self.rules.len() != len
+    }
+
+    /// Names of the rules, in the order they run
+    pub fn rules(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.rules.iter().map(|(rule, _)| rule.name())
+    }
+
+    /// Check `dmm` and every tile of it. Diagnostics are ordered by z, y then x, those without
+    /// a tile first, then by rule.
+    pub fn lint(&self, dmm: &DMM) -> Vec<Diagnostic> {
+        let tiles: Tiles = dmm.iter().collect();
+        let mut sorted: Vec<_> = tiles
+            .iter()
+            .map(|(&coords, &datums)| (coords, datums))
+            .collect();
+        sorted.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+
+        let mut diagnostics = Vec::new();
+        let mut map_findings = Vec::new();
+        for (rule, severity) in &self.rules {
+            rule.check_map(dmm, &mut map_findings);
+            diagnostics.extend(map_findings.drain(..).map(|(coords, message)| Diagnostic {
+                rule: rule.name(),
+                severity: *severity,
+                coords,
+                message,
+            }));
+        }
+
+        let mut findings = Vec::new();
+        for (coords, datums) in sorted {
+            let tile = Tile {
+                coords,
+                datums,
+                tiles: &tiles,
+            };
+            for (rule, severity) in &self.rules {
+                rule.check(&tile, &mut findings);
+                diagnostics.extend(findings.drain(..).map(|message| Diagnostic {
+                    rule: rule.name(),
+                    severity: *severity,
+                    coords: Some(coords),
+                    message,
+                }));
+            }
+        }
+
+        // Map findings go with the tile findings, before those of later rules
+        let order: Vec<_> = self.rules().collect();
+        diagnostics.sort_by_key(|d| {
+            let rule = order.iter().position(|&name| name == d.rule);
+            (d.coords.map(|(x, y, z)| (z, y, x)), rule)
+        });
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::EAST;
+    use crate::test_map;
+
+    /// Reports floors next to walls in the east
+    struct WallEast;
+
+    impl Rule for WallEast {
+        fn name(&self) -> &'static str {
+            "wall-east"
+        }
+
+        fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+            let is_wall = |datums: &[Datum]| datums.iter().any(|d| d.is_subtype_of("/turf/wall"));
+            if !is_wall(tile.datums) && tile.neighbour(EAST).is_some_and(is_wall) {
+                findings.push("wall in the east".to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        let floor = || vec![Datum::new("/turf/floor")];
+        let dmm = test_map(&[
+            ((1, 1, 1), floor()),
+            ((1, 2, 1), floor()),
+            ((2, 1, 1), vec![Datum::new("/turf/wall")]),
+            ((2, 2, 1), floor()),
+        ]);
+
+        let mut linter = Linter::default();
+        linter.add(WallEast);
+        assert!(linter.set_severity("wall-east", Severity::Error));
+        assert!(linter.remove("empty-entry"));
+        assert!(!linter.remove("empty-entry"));
+        let diagnostics: Vec<_> = linter
+            .lint(&dmm)
+            .into_iter()
+            .map(|d| (d.rule, d.severity, d.coords.unwrap()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("missing-area", Severity::Error, (1, 1, 1)),
+                ("wall-east", Severity::Error, (1, 1, 1)),
+                ("missing-area", Severity::Error, (2, 1, 1)),
+                ("missing-area", Severity::Error, (1, 2, 1)),
+                ("missing-area", Severity::Error, (2, 2, 1)),
+            ]
+        );
+    }
+}
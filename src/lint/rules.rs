@@ -0,0 +1,352 @@
+//! Built-in lint rules.
+
+use std::collections::HashMap;
+
+use super::{MapFinding, Rule, Severity, Tile};
+use crate::{Datum, Literal, DMM};
+
+/// Every built-in rule, with its default configuration
+pub fn builtin() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(MultipleTurfs),
+        Box::new(MissingArea),
+        Box::new(MultipleAreas),
+        Box::new(StackedDuplicates),
+        Box::new(RedundantVarEdit::default()),
+        Box::new(EmptyEntry),
+    ]
+}
+
+/// More than one turf on a tile
+pub struct MultipleTurfs;
+
+impl Rule for MultipleTurfs {
+    fn name(&self) -> &'static str {
+        "multiple-turfs"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        let turfs: Vec<_> = tile.turfs().map(Datum::path).collect();
+        if turfs.len() > 1 {
+            findings.push(format!("{} turfs: {}", turfs.len(), turfs.join(", ")));
+        }
+    }
+}
+
+/// Tile without an area. Empty tiles are left to [`EmptyEntry`].
+pub struct MissingArea;
+
+impl Rule for MissingArea {
+    fn name(&self) -> &'static str {
+        "missing-area"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        if !tile.datums.is_empty() && tile.areas().next().is_none() {
+            findings.push("no area".to_string());
+        }
+    }
+}
+
+/// More than one area on a tile
+pub struct MultipleAreas;
+
+impl Rule for MultipleAreas {
+    fn name(&self) -> &'static str {
+        "multiple-areas"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        let areas: Vec<_> = tile.areas().map(Datum::path).collect();
+        if areas.len() > 1 {
+            findings.push(format!("{} areas: {}", areas.len(), areas.join(", ")));
+        }
+    }
+}
+
+/// Identical objects stacked on a tile
+pub struct StackedDuplicates;
+
+impl Rule for StackedDuplicates {
+    fn name(&self) -> &'static str {
+        "stacked-duplicates"
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        let movables: Vec<_> = tile.datums.iter().filter(|d| d.is_movable()).collect();
+        for (i, datum) in movables.iter().enumerate() {
+            if movables[..i].contains(datum) {
+                continue;
+            }
+            let count = movables[i..].iter().filter(|&other| other == datum).count();
+            if count > 1 {
+                findings.push(format!("{} identical {}", count, datum.path()));
+            }
+        }
+    }
+}
+
+/// Default value of a var for a type and its subtypes
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarDefault {
+    pub path: String,
+    pub var: String,
+    pub value: Literal,
+}
+
+/// Var edit setting the value the var already has. The default value of a var is the one given
+/// for the closest parent type of the datum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedundantVarEdit {
+    pub defaults: Vec<VarDefault>,
+}
+
+impl Default for RedundantVarEdit {
+    /// Defaults of the built-in vars of BYOND. Types of the codebase may override them, such as
+    /// wall mounted objects with a pixel offset, in which case they should be given as well.
+    fn default() -> Self {
+        let mut defaults = Vec::new();
+        for &path in &["/area", "/turf", "/obj", "/mob"] {
+            for &(var, value) in &[
+                ("dir", 2),
+                ("pixel_x", 0),
+                ("pixel_y", 0),
+                ("pixel_w", 0),
+                ("pixel_z", 0),
+            ] {
+                defaults.push(VarDefault {
+                    path: path.to_string(),
+                    var: var.to_string(),
+                    value: Literal::Number(value),
+                });
+            }
+        }
+        RedundantVarEdit { defaults }
+    }
+}
+
+impl RedundantVarEdit {
+    fn default_of(&self, datum: &Datum, var: &str) -> Option<&Literal> {
+        self.defaults
+            .iter()
+            .filter(|default| default.var == var && datum.is_subtype_of(&default.path))
+            .max_by_key(|default| default.path.len())
+            .map(|default| &default.value)
+    }
+}
+
+/// Whether two literals are equal, numbers being compared by value
+fn same_value(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+        (Literal::Number(n), Literal::Float(f)) | (Literal::Float(f), Literal::Number(n)) => {
+            *n as f64 == *f
+        }
+        _ => a == b,
+    }
+}
+
+impl Rule for RedundantVarEdit {
+    fn name(&self) -> &'static str {
+        "redundant-var-edit"
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        for datum in tile.datums {
+            let mut vars: Vec<_> = datum.var_edits().iter().collect();
+            vars.sort_unstable_by_key(|(var, _)| *var);
+            for (var, value) in vars {
+                if self
+                    .default_of(datum, var)
+                    .is_some_and(|default| same_value(default, value))
+                {
+                    findings.push(format!(
+                        "{}: {} is set to its default value",
+                        datum.path(),
+                        var
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Dictionary entry without datum, reported once at the first tile using it, if any
+pub struct EmptyEntry;
+
+impl Rule for EmptyEntry {
+    fn name(&self) -> &'static str {
+        "empty-entry"
+    }
+
+    fn check_map(&self, dmm: &DMM, findings: &mut Vec<MapFinding>) {
+        let mut first_uses = HashMap::new();
+        for ((x, y, z), key) in dmm.tile_keys() {
+            let first = first_uses.entry(key).or_insert((z, y, x));
+            *first = (*first).min((z, y, x));
+        }
+        let width = dmm.dictionary.keys().map(|key| key.width()).max();
+        let mut empty: Vec<_> = dmm
+            .dictionary
+            .iter()
+            .filter(|(_, datums)| datums.is_empty())
+            .map(|(key, _)| *key)
+            .collect();
+        empty.sort_unstable();
+        for key in empty {
+            let coords = first_uses.get(&key).map(|&(z, y, x)| (x, y, z));
+            let key = key.to_str_with_width(width.unwrap_or(1));
+            findings.push((coords, format!("empty dictionary entry \"{}\"", key)));
+        }
+    }
+}
+
+/// Datums of types not allowed on the map. Not a built-in rule, as it depends on the map.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathPolicy {
+    /// Types allowed, with their subtypes. Every type is allowed when empty.
+    pub allowed: Vec<String>,
+    /// Types banned, with their subtypes, even when they are allowed
+    pub banned: Vec<String>,
+}
+
+impl Rule for PathPolicy {
+    fn name(&self) -> &'static str {
+        "path-policy"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, tile: &Tile<'_>, findings: &mut Vec<String>) {
+        let is_any = |datum: &Datum, paths: &[String]| paths.iter().any(|p| datum.is_subtype_of(p));
+        for datum in tile.datums {
+            if is_any(datum, &self.banned) {
+                findings.push(format!("{} is banned", datum.path()));
+            } else if !self.allowed.is_empty() && !is_any(datum, &self.allowed) {
+                findings.push(format!("{} is not allowed", datum.path()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Linter;
+    use super::*;
+    use crate::test_map;
+
+    #[test]
+    fn test_builtin_rules() {
+        let pen = || Datum::new("/obj/item/pen");
+        let light = Datum::with_var_edits(
+            "/obj/machinery/light",
+            vec![
+                ("dir".to_string(), Literal::Number(2)),
+                ("pixel_y".to_string(), Literal::Float(0.0)),
+                ("pixel_x".to_string(), Literal::Number(24)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut dmm = test_map(&[
+            (
+                (1, 1, 1),
+                vec![
+                    pen(),
+                    light,
+                    pen(),
+                    Datum::new("/turf/open/floor"),
+                    Datum::new("/turf/closed/wall"),
+                    Datum::new("/area/bridge"),
+                ],
+            ),
+            (
+                (1, 2, 1),
+                vec![Datum::new("/area/bridge"), Datum::new("/area/hallway")],
+            ),
+            ((1, 3, 1), vec![]),
+        ]);
+        // Unused entries are reported too
+        dmm.dictionary.insert(crate::Key::new(9), Vec::new());
+
+        let diagnostics: Vec<_> = Linter::default()
+            .lint(&dmm)
+            .into_iter()
+            .map(|d| (d.rule, d.coords.map(|(_, y, _)| y), d.message))
+            .collect();
+        let expected = vec![
+            ("empty-entry", None, "empty dictionary entry \"j\""),
+            (
+                "multiple-turfs",
+                Some(1),
+                "2 turfs: /turf/open/floor, /turf/closed/wall",
+            ),
+            ("stacked-duplicates", Some(1), "2 identical /obj/item/pen"),
+            (
+                "redundant-var-edit",
+                Some(1),
+                "/obj/machinery/light: dir is set to its default value",
+            ),
+            (
+                "redundant-var-edit",
+                Some(1),
+                "/obj/machinery/light: pixel_y is set to its default value",
+            ),
+            (
+                "multiple-areas",
+                Some(2),
+                "2 areas: /area/bridge, /area/hallway",
+            ),
+            ("empty-entry", Some(3), "empty dictionary entry \"c\""),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(rule, y, message)| (rule, y, message.to_string()))
+            .collect();
+        assert_eq!(diagnostics, expected);
+    }
+
+    #[test]
+    fn test_path_policy() {
+        let dmm = test_map(&[(
+            (1, 1, 1),
+            [
+                Datum::new("/obj/item/gun/energy"),
+                Datum::new("/obj/machinery/door"),
+                Datum::new("/turf/open/floor"),
+                Datum::new("/area/shuttle/escape"),
+            ],
+        )]);
+        let mut linter = Linter::empty();
+        linter.add(PathPolicy {
+            allowed: vec![
+                "/obj/item".to_string(),
+                "/turf".to_string(),
+                "/area/shuttle".to_string(),
+            ],
+            banned: vec!["/obj/item/gun".to_string()],
+        });
+
+        let messages: Vec<_> = linter.lint(&dmm).into_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "/obj/item/gun/energy is banned",
+                "/obj/machinery/door is not allowed"
+            ]
+        );
+    }
+}
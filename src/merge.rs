@@ -0,0 +1,250 @@
+//! Three-way merge of maps.
+//!
+//! Like [`diff`](crate::diff()), the merge compares the resolved content of the tiles. A tile
+//! changed on one side only takes that change, a tile changed differently on both sides is a
+//! conflict.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::diff::same_tile;
+use crate::{Datum, Key, DMM};
+
+/// A tile changed differently by both sides
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub coords: (u32, u32, u32),
+    /// Content of the tile in each map, empty if the tile does not exist there
+    pub base: Vec<Datum>,
+    pub ours: Vec<Datum>,
+    pub theirs: Vec<Datum>,
+}
+
+/// How to resolve a conflict
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Keep our tile and put this datum on it, such as `/obj/merge_conflict_marker`
+    Marker(Datum),
+}
+
+/// Result of a merge
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    merged: DMM,
+    conflicts: Vec<Conflict>,
+}
+
+impl Merge {
+    /// The merged map, where unresolved conflicts have our tile
+    pub fn merged(&self) -> &DMM {
+        &self.merged
+    }
+
+    /// Unresolved conflicts, ordered by z, y then x
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Resolve the conflict at `coords`. Returns `false` if there is no conflict there.
+    pub fn resolve(&mut self, coords: (u32, u32, u32), resolution: &Resolution) -> bool {
+        match self.conflicts.iter().position(|c| c.coords == coords) {
+            Some(index) => {
+                let conflict = self.conflicts.remove(index);
+                self.apply(conflict, resolution);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Resolve every remaining conflict the same way
+    pub fn resolve_all(&mut self, resolution: &Resolution) {
+        for conflict in std::mem::take(&mut self.conflicts) {
+            self.apply(conflict, resolution);
+        }
+    }
+
+    pub fn into_merged(self) -> DMM {
+        self.merged
+    }
+
+    fn apply(&mut self, conflict: Conflict, resolution: &Resolution) {
+        let tile = match resolution {
+            Resolution::Ours => conflict.ours,
+            Resolution::Theirs => conflict.theirs,
+            Resolution::Marker(marker) => {
+                let mut tile = conflict.ours;
+                tile.insert(0, marker.clone());
+                tile
+            }
+        };
+        if tile.is_empty() {
+            let mut tiles = self.merged.tile_keys();
+            tiles.remove(&conflict.coords);
+            self.merged.set_tile_keys(tiles);
+        } else {
+            self.merged.set_tile(conflict.coords, &tile);
+        }
+        self.merged.prune_dictionary();
+    }
+}
+
+/// Merge the changes made by `ours` and `theirs` to `base`.
+///
+/// The merged map uses the keys of `ours`, so that it differs as little as possible from it.
+pub fn merge(base: &DMM, ours: &DMM, theirs: &DMM) -> Merge {
+    let base_tiles = base.tile_keys();
+    let our_tiles = ours.tile_keys();
+    let their_tiles = theirs.tile_keys();
+    let coords: BTreeSet<_> = base_tiles
+        .keys()
+        .chain(our_tiles.keys())
+        .chain(their_tiles.keys())
+        .map(|&(x, y, z)| (z, y, x))
+        .collect();
+
+    let mut merged = ours.clone();
+    let their_keys = merged.import_keys(theirs, &theirs.used_keys());
+    let mut tiles = our_tiles.clone();
+    let mut conflicts = Vec::new();
+    for coords in coords.into_iter().map(|(z, y, x)| (x, y, z)) {
+        let base_tile = content(base, &base_tiles, coords);
+        let our_tile = content(ours, &our_tiles, coords);
+        let their_tile = content(theirs, &their_tiles, coords);
+
+        if same_tile(our_tile, their_tile) || same_tile(their_tile, base_tile) {
+            continue;
+        }
+        if same_tile(our_tile, base_tile) {
+            match their_tiles.get(&coords) {
+                Some(key) => tiles.insert(coords, their_keys[key]),
+                None => tiles.remove(&coords),
+            };
+        } else {
+            conflicts.push(Conflict {
+                coords,
+                base: base_tile.to_vec(),
+                ours: our_tile.to_vec(),
+                theirs: their_tile.to_vec(),
+            });
+        }
+    }
+
+    merged.set_tile_keys(tiles);
+    merged.prune_dictionary();
+    Merge { merged, conflicts }
+}
+
+/// Content of the tile at `coords`, empty if there is none
+fn content<'d>(
+    map: &'d DMM,
+    tiles: &HashMap<(u32, u32, u32), Key>,
+    coords: (u32, u32, u32),
+) -> &'d [Datum] {
+    tiles
+        .get(&coords)
+        .and_then(|key| map.dictionary.get(key))
+        .map_or(&[], Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    fn column(turfs: &[&str]) -> DMM {
+        let tiles: Vec<_> = (1..)
+            .zip(turfs)
+            .map(|(y, turf)| ((1, y, 1), [Datum::new(*turf)]))
+            .collect();
+        test_map(&tiles)
+    }
+
+    fn turfs(dmm: &DMM) -> Vec<&str> {
+        (1..=dmm.size().1)
+            .map(|y| dmm.tile((1, y, 1)).unwrap()[0].path())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_merge() {
+        let base = column(&["/turf/a", "/turf/b", "/turf/c"]);
+        let ours = column(&["/turf/x", "/turf/b", "/turf/c"]);
+        let theirs = column(&["/turf/a", "/turf/b", "/turf/y"]);
+
+        let merge = merge(&base, &ours, &theirs);
+        assert!(merge.is_clean());
+        assert_eq!(turfs(merge.merged()), vec!["/turf/x", "/turf/b", "/turf/y"]);
+        assert_eq!(merge.merged().dictionary().len(), 3);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let base = column(&["/turf/a", "/turf/b"]);
+        let ours = column(&["/turf/x", "/turf/c"]);
+        let theirs = column(&["/turf/y", "/turf/c"]);
+
+        let mut merge = merge(&base, &ours, &theirs);
+        assert_eq!(
+            merge.conflicts(),
+            &[Conflict {
+                coords: (1, 1, 1),
+                base: vec![Datum::new("/turf/a")],
+                ours: vec![Datum::new("/turf/x")],
+                theirs: vec![Datum::new("/turf/y")],
+            }]
+        );
+        assert!(!merge.resolve((1, 2, 1), &Resolution::Theirs));
+
+        let mut with_marker = merge.clone();
+        with_marker.resolve_all(&Resolution::Marker(Datum::new(
+            "/obj/merge_conflict_marker",
+        )));
+        assert_eq!(
+            with_marker.merged().tile((1, 1, 1)).unwrap(),
+            &[
+                Datum::new("/obj/merge_conflict_marker"),
+                Datum::new("/turf/x")
+            ][..]
+        );
+
+        assert!(merge.resolve((1, 1, 1), &Resolution::Theirs));
+        assert!(merge.is_clean());
+        assert_eq!(turfs(&merge.into_merged()), vec!["/turf/y", "/turf/c"]);
+    }
+
+    #[test]
+    fn test_reordered_tile_is_unchanged() {
+        let tile = |datums: &[&str]| datums.iter().map(|p| Datum::new(*p)).collect::<Vec<_>>();
+        let base = test_map(&[((1, 1, 1), tile(&["/obj/a", "/obj/b", "/turf/a"]))]);
+        let ours = test_map(&[((1, 1, 1), tile(&["/obj/b", "/obj/a", "/turf/a"]))]);
+        let theirs = test_map(&[((1, 1, 1), tile(&["/obj/a", "/obj/b", "/turf/x"]))]);
+
+        let merge = merge(&base, &ours, &theirs);
+        assert!(merge.is_clean());
+        assert_eq!(
+            merge.merged().tile((1, 1, 1)).unwrap(),
+            &tile(&["/obj/a", "/obj/b", "/turf/x"])[..]
+        );
+    }
+
+    #[test]
+    fn test_resolve_to_deleted_tile() {
+        let base = column(&["/turf/a", "/turf/b"]);
+        let ours = column(&["/turf/a"]);
+        let theirs = column(&["/turf/a", "/turf/y"]);
+
+        let mut merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts().len(), 1);
+        merge.resolve_all(&Resolution::Ours);
+        let merged = merge.into_merged();
+        assert_eq!(merged.tile((1, 2, 1)), None);
+        assert_eq!(merged.size(), (1, 1, 1));
+        assert_eq!(merged.dictionary().len(), 1);
+    }
+}
@@ -0,0 +1,304 @@
+//! Tile level patches.
+//!
+//! A [`Patch`] records the resolved content of the changed tiles, so it does not depend on the
+//! keys of the map it was made from and can be applied to another copy of the same map, like a
+//! fork using different keys.
+//!
+//! With the `serde` feature, patches can be saved and shared as JSON. Each datum is its path
+//! and var edits, var values being tagged with their kind:
+//!
+//! ```json
+//! {"operations": [{
+//!     "coords": [1, 1, 1],
+//!     "old": [{"path": "/turf/a", "var_edits": {}}],
+//!     "new": [
+//!         {"path": "/obj/light", "var_edits": {"dir": {"Number": 4}, "name": {"Str": "lamp"}}},
+//!         {"path": "/turf/a", "var_edits": {}}
+//!     ],
+//!     "context": [[], [], [], [], [], [], [], []]
+//! }]}
+//! ```
+//!
+//! `context` always has the 8 neighbours of the tile, an empty list standing for a missing tile.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::diff::same_tile;
+use crate::{Datum, DMM};
+
+/// Offsets of the tiles around a tile, on the same z-level, in the order of
+/// [`TileOperation::context`]
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Replacement of the content of one tile. Empty contents mean the tile does not exist.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct TileOperation {
+    pub coords: (u32, u32, u32),
+    pub old: Vec<Datum>,
+    pub new: Vec<Datum>,
+    /// Content of the 8 surrounding tiles before the change, used to find the tile when it moved
+    pub context: Vec<Vec<Datum>>,
+}
+
+/// Changes between two maps, as tile operations ordered by z, y then x
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Patch {
+    pub operations: Vec<TileOperation>,
+}
+
+/// Outcome of [`Patch::apply`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Applied {
+    /// Index of each applied operation, with the coordinates it was applied at
+    pub applied: Vec<(usize, (u32, u32, u32))>,
+    /// Index of the operations whose tile was not found
+    pub rejected: Vec<usize>,
+}
+
+impl Applied {
+    pub fn is_complete(&self) -> bool {
+        self.rejected.is_empty()
+    }
+}
+
+impl Patch {
+    /// Patch turning `old` into `new`
+    pub fn new(old: &DMM, new: &DMM) -> Self {
+        let old_tiles: HashMap<_, _> = old.iter().collect();
+        let operations = crate::diff(old, new)
+            .tiles
+            .into_iter()
+            .map(|tile| TileOperation {
+                coords: tile.coords,
+                old: content(&old_tiles, Some(tile.coords)).to_vec(),
+                new: new.tile(tile.coords).unwrap_or_default().to_vec(),
+                context: NEIGHBOURS
+                    .iter()
+                    .map(|&offset| content(&old_tiles, shift(tile.coords, offset)).to_vec())
+                    .collect(),
+            })
+            .collect();
+        Patch { operations }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Apply the operations whose tile is found in `dmm`.
+    ///
+    /// An operation applies where the tile still has its old content. When it does not, the
+    /// tiles up to `fuzz` tiles away on x and y are tried, the closest first. They must have the
+    /// old content and be surrounded by the old context. Every operation is matched against
+    /// `dmm` as it was before applying the patch.
+    pub fn apply(&self, dmm: &mut DMM, fuzz: u32) -> Applied {
+        let mut applied = Applied::default();
+        let mut changes = Vec::new();
+        {
+            let tiles: HashMap<_, _> = dmm.iter().collect();
+            for (index, operation) in self.operations.iter().enumerate() {
+                match find(&tiles, operation, fuzz) {
+                    Some(coords) => {
+                        applied.applied.push((index, coords));
+                        changes.push((coords, &operation.new));
+                    }
+                    None => applied.rejected.push(index),
+                }
+            }
+        }
+
+        let mut tiles = dmm.tile_keys();
+        for (coords, new) in changes {
+            if new.is_empty() {
+                tiles.remove(&coords);
+            } else {
+                tiles.insert(coords, dmm.key_for(new));
+            }
+        }
+        dmm.set_tile_keys(tiles);
+        dmm.prune_dictionary();
+        applied
+    }
+}
+
+type Tiles<'d> = HashMap<(u32, u32, u32), &'d [Datum]>;
+
+/// Where to apply `operation`
+fn find(tiles: &Tiles, operation: &TileOperation, fuzz: u32) -> Option<(u32, u32, u32)> {
+    if same_tile(content(tiles, Some(operation.coords)), &operation.old) {
+        return Some(operation.coords);
+    }
+
+    let fuzz = i64::from(fuzz);
+    (1..=fuzz)
+        .flat_map(|distance| {
+            (-distance..=distance).flat_map(move |dy| {
+                (-distance..=distance)
+                    .filter(move |dx| dx.abs().max(dy.abs()) == distance)
+                    .map(move |dx| (dx, dy))
+            })
+        })
+        .filter_map(|offset| shift(operation.coords, offset))
+        .find(|&coords| {
+            same_tile(content(tiles, Some(coords)), &operation.old)
+                && NEIGHBOURS
+                    .iter()
+                    .zip(&operation.context)
+                    .all(|(&offset, old)| same_tile(content(tiles, shift(coords, offset)), old))
+        })
+}
+
+fn shift((x, y, z): (u32, u32, u32), (dx, dy): (i64, i64)) -> Option<(u32, u32, u32)> {
+    let x = i64::from(x) + dx;
+    let y = i64::from(y) + dy;
+    if x < 1 || y < 1 {
+        None
+    } else {
+        Some((x as u32, y as u32, z))
+    }
+}
+
+/// Content of the tile at `coords`, empty if there is none
+fn content<'d>(tiles: &Tiles<'d>, coords: Option<(u32, u32, u32)>) -> &'d [Datum] {
+    coords
+        .and_then(|coords| tiles.get(&coords))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    /// Map of a single row of turfs
+    fn row(turfs: &[&str]) -> DMM {
+        let tiles: Vec<_> = (1..)
+            .zip(turfs)
+            .map(|(x, turf)| ((x, 1, 1), [Datum::new(*turf)]))
+            .collect();
+        test_map(&tiles)
+    }
+
+    fn turfs(dmm: &DMM) -> Vec<&str> {
+        (1..=dmm.size().0)
+            .map(|x| dmm.tile((x, 1, 1)).unwrap()[0].path())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let old = row(&["/turf/a", "/turf/b", "/turf/c"]);
+        let new = row(&["/turf/a", "/turf/x", "/turf/c"]);
+        let patch = Patch::new(&old, &new);
+        assert_eq!(patch.operations.len(), 1);
+
+        // Same map with other keys
+        let mut fork = test_map(&[
+            ((4, 1, 1), [Datum::new("/turf/d")]),
+            ((3, 1, 1), [Datum::new("/turf/c")]),
+            ((2, 1, 1), [Datum::new("/turf/b")]),
+            ((1, 1, 1), [Datum::new("/turf/a")]),
+        ]);
+        let applied = patch.apply(&mut fork, 0);
+        assert_eq!(applied.applied, vec![(0, (2, 1, 1))]);
+        assert_eq!(
+            turfs(&fork),
+            vec!["/turf/a", "/turf/x", "/turf/c", "/turf/d"]
+        );
+    }
+
+    #[test]
+    fn test_apply_fuzzy() {
+        let old = row(&["/turf/a", "/turf/b", "/turf/c"]);
+        let new = row(&["/turf/a", "/turf/x", "/turf/c"]);
+        let patch = Patch::new(&old, &new);
+
+        // The fork has an extra tile before
+        let mut shifted = row(&["/turf/d", "/turf/a", "/turf/b", "/turf/c"]);
+        assert_eq!(patch.apply(&mut shifted.clone(), 0).rejected, vec![0]);
+        let applied = patch.apply(&mut shifted, 1);
+        assert_eq!(applied.applied, vec![(0, (3, 1, 1))]);
+        assert_eq!(
+            turfs(&shifted),
+            vec!["/turf/d", "/turf/a", "/turf/x", "/turf/c"]
+        );
+
+        // /turf/b at (1,1,1) is closer, but not surrounded by /turf/a and /turf/c
+        let mut decoy = row(&["/turf/b", "/turf/d", "/turf/a", "/turf/b", "/turf/c"]);
+        assert_eq!(patch.apply(&mut decoy, 2).applied, vec![(0, (4, 1, 1))]);
+    }
+
+    #[test]
+    fn test_apply_reordered() {
+        let tile = |datums: &[&str]| datums.iter().map(|p| Datum::new(*p)).collect::<Vec<_>>();
+        let old = test_map(&[
+            ((1, 1, 1), tile(&["/obj/a", "/turf/a"])),
+            ((2, 1, 1), tile(&["/obj/b", "/turf/b"])),
+        ]);
+        let new = test_map(&[
+            ((1, 1, 1), tile(&["/obj/a", "/turf/a"])),
+            ((2, 1, 1), tile(&["/turf/x"])),
+        ]);
+        let patch = Patch::new(&old, &new);
+
+        // The fork holds the same datums in another order
+        let mut fork = test_map(&[
+            ((1, 1, 1), tile(&["/turf/a", "/obj/a"])),
+            ((2, 1, 1), tile(&["/turf/b", "/obj/b"])),
+        ]);
+        assert_eq!(patch.apply(&mut fork, 0).applied, vec![(0, (2, 1, 1))]);
+        assert_eq!(fork.tile((2, 1, 1)).unwrap(), &tile(&["/turf/x"])[..]);
+
+        // Reordered context, one tile further
+        let mut shifted = test_map(&[
+            ((1, 1, 1), tile(&["/turf/d"])),
+            ((2, 1, 1), tile(&["/turf/a", "/obj/a"])),
+            ((3, 1, 1), tile(&["/turf/b", "/obj/b"])),
+        ]);
+        assert_eq!(patch.apply(&mut shifted, 1).applied, vec![(0, (3, 1, 1))]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let old = row(&["/turf/a", "/turf/b"]);
+        let mut light = Datum::new("/obj/light");
+        light
+            .var_edits
+            .insert("dir".to_string(), crate::Literal::Number(4));
+        let new = test_map(&[
+            ((1, 1, 1), vec![light, Datum::new("/turf/a")]),
+            ((2, 1, 1), vec![Datum::new("/turf/b")]),
+        ]);
+        let patch = Patch::new(&old, &new);
+
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch);
+
+        let documented = r#"{"operations": [{
+            "coords": [1, 1, 1],
+            "old": [{"path": "/turf/a", "var_edits": {}}],
+            "new": [
+                {"path": "/obj/light", "var_edits": {"dir": {"Number": 4}}},
+                {"path": "/turf/a", "var_edits": {}}
+            ],
+            "context": [[], [], [], [], [{"path": "/turf/b", "var_edits": {}}], [], [], []]
+        }]}"#;
+        assert_eq!(serde_json::from_str::<Patch>(documented).unwrap(), patch);
+    }
+}
@@ -0,0 +1,80 @@
+//! Search of datums on a map.
+
+use crate::edit::Predicate;
+use crate::{Datum, DMM};
+
+/// A datum found on a map
+#[derive(Clone, Debug, PartialEq)]
+pub struct Found<'d> {
+    pub coords: (u32, u32, u32),
+    /// Area of the tile
+    pub area: Option<&'d Datum>,
+    pub datum: &'d Datum,
+}
+
+impl DMM {
+    /// Every placed datum matching `predicate`, such as a [`Filter`](crate::edit::Filter),
+    /// ordered by z, y then x
+    pub fn find<P: Predicate + ?Sized>(&self, predicate: &P) -> Vec<Found<'_>> {
+        let mut tiles: Vec<_> = self.iter().collect();
+        tiles.sort_unstable_by_key(|&((x, y, z), _)| (z, y, x));
+
+        let mut found = Vec::new();
+        for (coords, tile) in tiles {
+            let area = tile.iter().find(|d| d.is_area());
+            found.extend(
+                tile.iter()
+                    .filter(|datum| predicate.matches(datum, tile))
+                    .map(|datum| Found {
+                        coords,
+                        area,
+                        datum,
+                    }),
+            );
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::Filter;
+    use crate::{test_map, Literal};
+
+    #[test]
+    fn test_find() {
+        let door = Datum::with_var_edits(
+            "/obj/machinery/door/airlock/command",
+            vec![("req_access".to_string(), Literal::Number(19))]
+                .into_iter()
+                .collect(),
+        );
+        let bridge = Datum::new("/area/bridge");
+        let dmm = test_map(&[
+            ((1, 1, 1), vec![Datum::new("/area/hallway")]),
+            ((1, 2, 1), vec![door.clone(), bridge.clone()]),
+            ((2, 1, 1), vec![door.clone(), bridge.clone()]),
+        ]);
+
+        let found = dmm.find(&Filter::subtypes_of("/obj/machinery/door"));
+        assert_eq!(
+            found,
+            vec![
+                Found {
+                    coords: (2, 1, 1),
+                    area: Some(&bridge),
+                    datum: &door,
+                },
+                Found {
+                    coords: (1, 2, 1),
+                    area: Some(&bridge),
+                    datum: &door,
+                },
+            ]
+        );
+        assert!(dmm
+            .find(&Filter::glob("/obj/*").with_var("req_access", Literal::Number(20)))
+            .is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+//! Usage statistics of types and var edits.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Literal, DMM};
+
+/// Placed instances of a type, without its subtypes
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TypeUsage {
+    pub path: String,
+    pub count: usize,
+    /// Instances by area of their tile, `None` for tiles without an area
+    pub by_area: BTreeMap<Option<String>, usize>,
+    pub by_z: BTreeMap<u32, usize>,
+}
+
+/// Placed instances of a type with the same value given to a var
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarEditUsage {
+    pub path: String,
+    pub var: String,
+    pub value: Literal,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Types from the most placed, then by path
+    pub types: Vec<TypeUsage>,
+    /// Var edits from the most placed, then by path, var and order of appearance in the
+    /// dictionary
+    pub var_edits: Vec<VarEditUsage>,
+}
+
+impl DMM {
+    pub fn stats(&self) -> Stats {
+        let mut types: HashMap<&str, TypeUsage> = HashMap::new();
+        for ((_, _, z), tile) in self.iter() {
+            let area = tile.iter().find(|d| d.is_area()).map(|d| d.path());
+            for datum in tile {
+                let usage = types.entry(datum.path()).or_default();
+                usage.count += 1;
+                *usage.by_area.entry(area.map(str::to_string)).or_default() += 1;
+                *usage.by_z.entry(z).or_default() += 1;
+            }
+        }
+        let mut types: Vec<_> = types
+            .into_iter()
+            .map(|(path, usage)| TypeUsage {
+                path: path.to_string(),
+                ..usage
+            })
+            .collect();
+        types.sort_unstable_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+
+        // Values of each var of each type, counted once per dictionary entry then weighted by
+        // the uses of the entry
+        let uses = self.key_uses();
+        let mut entries: Vec<_> = self.dictionary.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| **key);
+        let mut values: HashMap<(&str, &str), Vec<(&Literal, usize)>> = HashMap::new();
+        for (key, datums) in entries {
+            let count = uses.get(key).copied().unwrap_or(0);
+            if count == 0 {
+                continue;
+            }
+            for datum in datums {
+                for (var, value) in datum.var_edits() {
+                    let values = values.entry((datum.path(), var)).or_default();
+                    match values.iter_mut().find(|(v, _)| *v == value) {
+                        Some((_, total)) => *total += count,
+                        None => values.push((value, count)),
+                    }
+                }
+            }
+        }
+        let mut var_edits: Vec<_> = values
+            .into_iter()
+            .flat_map(|((path, var), values)| {
+                values.into_iter().map(move |(value, count)| VarEditUsage {
+                    path: path.to_string(),
+                    var: var.to_string(),
+                    value: value.clone(),
+                    count,
+                })
+            })
+            .collect();
+        // Stable, to keep the values of a var in the order of the dictionary
+        var_edits.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.path.cmp(&b.path))
+                .then_with(|| a.var.cmp(&b.var))
+        });
+
+        Stats { types, var_edits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Datum, Key};
+
+    #[test]
+    fn test_stats() {
+        let light = |dir| {
+            Datum::with_var_edits(
+                "/obj/machinery/light",
+                vec![("dir".to_string(), Literal::Number(dir))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let floor = || Datum::new("/turf/open/floor");
+        let mut dmm = test_map(&[
+            (
+                (1, 1, 1),
+                vec![light(4), floor(), Datum::new("/area/bridge")],
+            ),
+            (
+                (1, 2, 1),
+                vec![light(4), floor(), Datum::new("/area/bridge")],
+            ),
+            ((1, 3, 1), vec![light(4), floor()]),
+            (
+                (1, 1, 2),
+                vec![light(8), floor(), Datum::new("/area/bridge")],
+            ),
+        ]);
+        // Unused entries are not counted
+        dmm.dictionary.insert(Key::new(3), vec![light(1)]);
+
+        let stats = dmm.stats();
+        let light = &stats.types[0];
+        assert_eq!(light.path, "/obj/machinery/light");
+        assert_eq!(light.count, 4);
+        assert_eq!(
+            light.by_area,
+            vec![(None, 1), (Some("/area/bridge".to_string()), 3)]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(light.by_z, vec![(1, 3), (2, 1)].into_iter().collect());
+        let types: Vec<_> = stats
+            .types
+            .iter()
+            .map(|t| (t.path.as_str(), t.count))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("/obj/machinery/light", 4),
+                ("/turf/open/floor", 4),
+                ("/area/bridge", 3)
+            ]
+        );
+
+        let var_edits: Vec<_> = stats
+            .var_edits
+            .iter()
+            .map(|v| (v.value.clone(), v.count))
+            .collect();
+        assert_eq!(
+            var_edits,
+            vec![(Literal::Number(4), 3), (Literal::Number(8), 1)]
+        );
+    }
+}
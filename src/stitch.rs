@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::{Datum, DMM};
+
+/// Axis along which maps are stitched
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Where a source map ended up in a stitched map
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Placement {
+    /// Added to the coordinates of the source map
+    pub offset: (u32, u32, u32),
+    /// Size of the source map
+    pub size: (u32, u32, u32),
+}
+
+impl Placement {
+    /// Coordinates in the stitched map of the tile `coords` of the source map
+    pub fn to_stitched(&self, coords: (u32, u32, u32)) -> (u32, u32, u32) {
+        (
+            coords.0 + self.offset.0,
+            coords.1 + self.offset.1,
+            coords.2 + self.offset.2,
+        )
+    }
+}
+
+/// Put `maps` one after the other along `axis`, the first one getting the lowest coordinates.
+///
+/// Identical dictionary entries are shared between the sources. When the maps do not have the
+/// same size on the other axes, the tiles they do not cover are filled with `filler`.
+/// Returns the stitched map and the placement of each source map, in order.
+pub fn stitch(maps: &[DMM], axis: Axis, filler: &[Datum]) -> (DMM, Vec<Placement>) {
+    let mut stitched = DMM::default();
+    let mut index = stitched.entry_index();
+    let mut tiles = HashMap::new();
+    let mut placements = Vec::with_capacity(maps.len());
+    let mut offset = (0, 0, 0);
+
+    for map in maps {
+        let size = map.size();
+        let placement = Placement { offset, size };
+        let mapping = stitched.import_keys_with(&mut index, map, &map.used_keys());
+        for (coords, key) in map.tile_keys() {
+            tiles.insert(placement.to_stitched(coords), mapping[&key]);
+        }
+        placements.push(placement);
+
+        match axis {
+            Axis::X => offset.0 += size.0,
+            Axis::Y => offset.1 += size.1,
+            Axis::Z => offset.2 += size.2,
+        }
+    }
+
+    let (width, height, depth) = placements.iter().fold((0, 0, 0), |size, p| {
+        (
+            size.0.max(p.offset.0 + p.size.0),
+            size.1.max(p.offset.1 + p.size.1),
+            size.2.max(p.offset.2 + p.size.2),
+        )
+    });
+    if tiles.len() < (width * height * depth) as usize {
+        let filler = stitched.key_for(filler);
+        for z in 1..=depth {
+            for y in 1..=height {
+                for x in 1..=width {
+                    tiles.entry((x, y, z)).or_insert(filler);
+                }
+            }
+        }
+    }
+
+    stitched.set_tile_keys(tiles);
+    (stitched, placements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    fn column(turfs: &[&str]) -> DMM {
+        let tiles: Vec<_> = (1..)
+            .zip(turfs)
+            .map(|(y, turf)| ((1, y, 1), [Datum::new(*turf)]))
+            .collect();
+        test_map(&tiles)
+    }
+
+    #[test]
+    fn test_stitch_x() {
+        let space = [Datum::new("/turf/open/space")];
+        let (dmm, placements) = stitch(
+            &[
+                column(&["/turf/a", "/turf/b"]),
+                column(&["/turf/b"]),
+                column(&["/turf/c", "/turf/a"]),
+            ],
+            Axis::X,
+            &space,
+        );
+
+        assert_eq!(dmm.size(), (3, 2, 1));
+        assert_eq!(
+            placements,
+            vec![
+                Placement {
+                    offset: (0, 0, 0),
+                    size: (1, 2, 1)
+                },
+                Placement {
+                    offset: (1, 0, 0),
+                    size: (1, 1, 1)
+                },
+                Placement {
+                    offset: (2, 0, 0),
+                    size: (1, 2, 1)
+                },
+            ]
+        );
+        // a, b, c and the filler
+        assert_eq!(dmm.dictionary().len(), 4);
+        let tiles: HashMap<_, _> = dmm.iter().collect();
+        assert_eq!(tiles[&(2, 1, 1)][0].path(), "/turf/b");
+        assert_eq!(tiles[&(2, 2, 1)], &space[..]);
+        assert_eq!(tiles[&(3, 2, 1)][0].path(), "/turf/a");
+    }
+
+    #[test]
+    fn test_stitch_z() {
+        let (dmm, placements) = stitch(&[column(&["/turf/a"]), column(&["/turf/b"])], Axis::Z, &[]);
+
+        assert_eq!(dmm.size(), (1, 1, 2));
+        assert_eq!(placements[1].to_stitched((1, 1, 1)), (1, 1, 2));
+        assert_eq!(dmm.dictionary().len(), 2);
+    }
+}
@@ -0,0 +1,247 @@
+//! Subtypes suggested from repeated var edits.
+//!
+//! Instances of a type placed many times with the same var edits are better written as a
+//! subtype defining these vars. Each suggestion comes with an UpdatePaths rule replacing the
+//! edited instances by the subtype.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::update_paths::{Matcher, Replacement, Rule, VarAction, VarFilter};
+use crate::{Datum, Literal, DMM};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    /// The edited instance, as placed on the maps
+    pub instance: Datum,
+    /// Number of placed instances
+    pub count: usize,
+    /// The suggested subtype, with the var edits of the instance as var definitions
+    pub subtype: Datum,
+}
+
+impl Suggestion {
+    /// Rule replacing the edited instances by the subtype, keeping their other var edits
+    pub fn rule(&self) -> Rule {
+        let mut vars: Vec<_> = self.instance.var_edits().iter().collect();
+        vars.sort_unstable_by_key(|(var, _)| *var);
+        Rule {
+            matcher: Matcher {
+                path: self.instance.path().to_string(),
+                subtypes: false,
+                filters: vars
+                    .iter()
+                    .map(|(var, value)| (var.to_string(), VarFilter::Equals((*value).clone())))
+                    .collect(),
+            },
+            replacements: vec![Replacement {
+                path: self.subtype.path().to_string(),
+                subtypes: false,
+                keep_old: true,
+                vars: vars
+                    .iter()
+                    .map(|(var, _)| (var.to_string(), VarAction::Skip))
+                    .collect(),
+            }],
+        }
+    }
+}
+
+/// Part of a type path for `literal`, such as `engine_room` for `"Engine Room"`
+fn path_segment(literal: &Literal) -> String {
+    let text = match literal {
+        Literal::Path(path) => path.rsplit('/').next().unwrap_or_default().to_string(),
+        Literal::Str(s) => s.replace("\\improper", "").replace("\\proper", ""),
+        Literal::Number(n) if *n < 0 => format!("neg{}", -n),
+        Literal::Number(n) => n.to_string(),
+        Literal::Float(f) if *f < 0.0 => format!("neg{}", -f),
+        Literal::Float(f) => f.to_string(),
+        Literal::Null => "null".to_string(),
+        Literal::List(_) => "list".to_string(),
+    };
+    let mut segment = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            segment.push(c.to_ascii_lowercase());
+        } else if !segment.ends_with('_') {
+            segment.push('_');
+        }
+    }
+    segment.trim_matches('_').to_string()
+}
+
+/// Name of the subtype of `instance`: its name if it is edited, otherwise its vars and values
+fn subtype_name(instance: &Datum) -> String {
+    let name = instance
+        .var_edit("name")
+        .filter(|name| name.as_str().is_some())
+        .map(path_segment)
+        .filter(|name| !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()));
+    if let Some(name) = name {
+        return name;
+    }
+    let mut vars: Vec<_> = instance.var_edits().iter().collect();
+    vars.sort_unstable_by_key(|(var, _)| *var);
+    vars.iter()
+        .map(|(var, value)| {
+            let value = path_segment(value);
+            if value.is_empty() {
+                var.to_string()
+            } else {
+                format!("{}_{}", var, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Suggest subtypes for the instances placed at least `min_count` times with the same var
+/// edits on `maps`. Suggestions are ordered from the most placed, then by path.
+pub fn suggest_subtypes(maps: &[DMM], min_count: usize) -> Vec<Suggestion> {
+    // Edited instances of each type, counted once per dictionary entry then weighted by the
+    // uses of the entry
+    let mut instances: HashMap<&str, Vec<(&Datum, usize)>> = HashMap::new();
+    for dmm in maps {
+        let uses = dmm.key_uses();
+        let mut entries: Vec<_> = dmm.dictionary.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| **key);
+        for (key, datums) in entries {
+            let count = uses.get(key).copied().unwrap_or(0);
+            for datum in datums.iter().filter(|d| !d.var_edits().is_empty()) {
+                let instances = instances.entry(datum.path()).or_default();
+                match instances.iter_mut().find(|(other, _)| *other == datum) {
+                    Some((_, total)) => *total += count,
+                    None => instances.push((datum, count)),
+                }
+            }
+        }
+    }
+
+    let mut found: Vec<_> = instances
+        .into_values()
+        .flatten()
+        .filter(|&(_, count)| count > 0 && count >= min_count)
+        .collect();
+    found.sort_by(|(a, a_count), (b, b_count)| {
+        b_count.cmp(a_count).then_with(|| a.path().cmp(b.path()))
+    });
+
+    let mut taken = HashSet::new();
+    found
+        .into_iter()
+        .map(|(instance, count)| {
+            let name = format!("{}/{}", instance.path(), subtype_name(instance));
+            // Instances of the same type may get the same name, or the name of another one
+            // with a suffix
+            let mut path = name.clone();
+            let mut suffix = 1;
+            while taken.contains(&path) {
+                suffix += 1;
+                path = format!("{}_{}", name, suffix);
+            }
+            taken.insert(path.clone());
+            Suggestion {
+                instance: instance.clone(),
+                count,
+                subtype: Datum::with_var_edits(path, instance.var_edits().clone()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_map;
+
+    #[test]
+    fn test_suggest_subtypes() {
+        let airlock = |name: &str| {
+            Datum::with_var_edits(
+                "/obj/machinery/door/airlock/engineering/glass",
+                vec![("name".to_string(), Literal::Str(name.to_string()))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let sign = Datum::with_var_edits(
+            "/obj/structure/sign",
+            vec![
+                ("pixel_y".to_string(), Literal::Number(-32)),
+                ("dir".to_string(), Literal::Number(4)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let plain_sign = Datum::new("/obj/structure/sign");
+        let dmm = test_map(&[
+            ((1, 1, 1), vec![airlock("Engine Room")]),
+            ((1, 2, 1), vec![airlock("Engine Room")]),
+            ((1, 3, 1), vec![airlock("\\improper Engine Room!")]),
+            ((1, 4, 1), vec![airlock("\\improper Engine Room!")]),
+            ((1, 5, 1), vec![sign.clone()]),
+            ((1, 6, 1), vec![plain_sign.clone()]),
+            ((1, 7, 1), vec![plain_sign.clone()]),
+            ((1, 8, 1), vec![plain_sign]),
+        ]);
+
+        let suggestions = suggest_subtypes(&[dmm.clone(), dmm], 2);
+        let paths: Vec<_> = suggestions
+            .iter()
+            .map(|s| (s.subtype.path(), s.count))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (
+                    "/obj/machinery/door/airlock/engineering/glass/engine_room",
+                    4
+                ),
+                (
+                    "/obj/machinery/door/airlock/engineering/glass/engine_room_2",
+                    4
+                ),
+                ("/obj/structure/sign/dir_4_pixel_y_neg32", 2),
+            ]
+        );
+
+        let rule = suggestions[2].rule();
+        assert_eq!(
+            rule.apply_to(&sign),
+            Some(vec![Datum::new("/obj/structure/sign/dir_4_pixel_y_neg32")])
+        );
+    }
+
+    #[test]
+    fn test_unique_paths() {
+        let door = |name: &str| {
+            Datum::with_var_edits(
+                "/obj/machinery/door",
+                vec![("name".to_string(), Literal::Str(name.to_string()))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let dmm = test_map(&[
+            ((1, 1, 1), [door("Room 2")]),
+            ((1, 2, 1), [door("Room 2")]),
+            ((1, 3, 1), [door("Room 2")]),
+            ((1, 4, 1), [door("Room")]),
+            ((1, 5, 1), [door("Room")]),
+            ((1, 6, 1), [door("\\improper Room")]),
+            ((1, 7, 1), [door("\\improper Room")]),
+        ]);
+
+        let paths: Vec<_> = suggest_subtypes(&[dmm], 2)
+            .into_iter()
+            .map(|s| s.subtype.path().to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/obj/machinery/door/room_2",
+                "/obj/machinery/door/room",
+                "/obj/machinery/door/room_3",
+            ]
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Path migrations, in the style of tgstation's UpdatePaths tool.
+//!
+//! A rule such as `/obj/old/@SUBTYPES{dir=4} : /obj/new/@SUBTYPES{@OLD; name=@SKIP}` replaces
+//! every matching datum with one or more new datums, or deletes it with `@DELETE`.
+//! The textual rules are parsed by `dmm-format`.
+
+use std::collections::HashMap;
+
+use crate::{Datum, Literal, DMM};
+
+/// A migration rule: every datum matched by `matcher` is replaced by `replacements`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub matcher: Matcher,
+    /// Datums replacing the matched one. Empty when the datum is deleted (`@DELETE`).
+    pub replacements: Vec<Replacement>,
+}
+
+/// Left-hand side of a rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matcher {
+    pub path: String,
+    /// Also match the subtypes of `path` (`/@SUBTYPES`)
+    pub subtypes: bool,
+    /// Conditions on the var edits of the datum
+    pub filters: Vec<(String, VarFilter)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VarFilter {
+    /// The var is edited to this value
+    Equals(Literal),
+    /// The var is not edited (`@UNSET`)
+    Unset,
+}
+
+/// Right-hand side of a rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replacement {
+    pub path: String,
+    /// Append the subtype part of the matched path (`/@SUBTYPES`)
+    pub subtypes: bool,
+    /// Copy every var edit of the matched datum (`@OLD`)
+    pub keep_old: bool,
+    /// Var edits applied after the copied ones, in order
+    pub vars: Vec<(String, VarAction)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VarAction {
+    /// Set the var to this value
+    Set(Literal),
+    /// Copy the value of a var of the matched datum, if it is edited (`@OLD` or `@OLD:var`)
+    Old(String),
+    /// Do not set the var (`@SKIP`)
+    Skip,
+}
+
+impl Matcher {
+    pub fn matches(&self, datum: &Datum) -> bool {
+        let path_matches = if self.subtypes {
+            datum.is_subtype_of(&self.path)
+        } else {
+            datum.path == self.path
+        };
+
+        path_matches
+            && self.filters.iter().all(|(var, filter)| match filter {
+                VarFilter::Equals(value) => datum.var_edit(var) == Some(value),
+                VarFilter::Unset => datum.var_edit(var).is_none(),
+            })
+    }
+}
+
+impl Rule {
+    /// Datums replacing `datum`, or `None` if the rule does not match it
+    pub fn apply_to(&self, datum: &Datum) -> Option<Vec<Datum>> {
+        if !self.matcher.matches(datum) {
+            return None;
+        }
+
+        let suffix = &datum.path[self.matcher.path.len()..];
+        let replacements = self
+            .replacements
+            .iter()
+            .map(|replacement| {
+                let mut path = replacement.path.clone();
+                if replacement.subtypes && self.matcher.subtypes {
+                    path.push_str(suffix);
+                }
+
+                let mut var_edits = if replacement.keep_old {
+                    datum.var_edits.clone()
+                } else {
+                    HashMap::new()
+                };
+                for (var, action) in &replacement.vars {
+                    match action {
+                        VarAction::Set(value) => {
+                            var_edits.insert(var.clone(), value.clone());
+                        }
+                        VarAction::Old(old_var) => {
+                            if let Some(value) = datum.var_edit(old_var) {
+                                var_edits.insert(var.clone(), value.clone());
+                            }
+                        }
+                        VarAction::Skip => {
+                            var_edits.remove(var);
+                        }
+                    }
+                }
+
+                Datum::with_var_edits(path, var_edits)
+            })
+            .collect();
+        Some(replacements)
+    }
+}
+
+impl DMM {
+    /// Apply the migration rules one after another.
+    ///
+    /// Returns, for each rule, the number of datum instances placed on the map it changed.
+    /// Dictionary entries that became identical are merged.
+    pub fn update_paths(&mut self, rules: &[Rule]) -> Vec<usize> {
+        let uses = self.key_uses();
+        let changes = rules
+            .iter()
+            .map(|rule| {
+                let mut changed = 0;
+                for (key, datums) in &mut self.dictionary {
+                    let key_uses = uses.get(key).copied().unwrap_or_default();
+                    *datums = datums
+                        .drain(..)
+                        .flat_map(|datum| match rule.apply_to(&datum) {
+                            Some(replacements) => {
+                                changed += key_uses;
+                                replacements
+                            }
+                            None => vec![datum],
+                        })
+                        .collect();
+                }
+                changed
+            })
+            .collect();
+        self.merge_duplicate_entries();
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Key};
+
+    fn airlock_rule() -> Rule {
+        Rule {
+            matcher: Matcher {
+                path: "/obj/machinery/door/airlock".to_string(),
+                subtypes: true,
+                filters: vec![("dir".to_string(), VarFilter::Unset)],
+            },
+            replacements: vec![Replacement {
+                path: "/obj/machinery/door/new_airlock".to_string(),
+                subtypes: true,
+                keep_old: true,
+                vars: vec![
+                    ("name".to_string(), VarAction::Skip),
+                    ("desc".to_string(), VarAction::Old("name".to_string())),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_apply_rule() {
+        let rule = airlock_rule();
+        let datum = Datum::with_var_edits(
+            "/obj/machinery/door/airlock/command",
+            vec![
+                ("name".to_string(), Literal::Str("Bridge".to_string())),
+                ("id_tag".to_string(), Literal::Str("bridge".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            rule.apply_to(&datum),
+            Some(vec![Datum::with_var_edits(
+                "/obj/machinery/door/new_airlock/command",
+                vec![
+                    ("desc".to_string(), Literal::Str("Bridge".to_string())),
+                    ("id_tag".to_string(), Literal::Str("bridge".to_string())),
+                ]
+                .into_iter()
+                .collect(),
+            )])
+        );
+        assert_eq!(
+            rule.apply_to(&Datum::new("/obj/machinery/door/airlocks")),
+            None
+        );
+
+        let mut rotated = datum;
+        rotated
+            .var_edits
+            .insert("dir".to_string(), Literal::Number(4));
+        assert_eq!(rule.apply_to(&rotated), None);
+    }
+
+    #[test]
+    fn test_update_paths() {
+        let airlock = vec![
+            Datum::new("/obj/machinery/door/airlock"),
+            Datum::new("/obj/item/trash"),
+            Datum::new("/turf/open/floor"),
+        ];
+        let mut dmm = test_map(&[
+            ((1, 1, 1), airlock.clone()),
+            ((1, 2, 1), airlock),
+            ((1, 3, 1), vec![Datum::new("/turf/open/floor")]),
+            (
+                (1, 4, 1),
+                vec![
+                    Datum::new("/obj/machinery/door/new_airlock"),
+                    Datum::new("/turf/open/floor"),
+                ],
+            ),
+        ]);
+        let delete_trash = Rule {
+            matcher: Matcher {
+                path: "/obj/item/trash".to_string(),
+                subtypes: false,
+                filters: Vec::new(),
+            },
+            replacements: Vec::new(),
+        };
+
+        assert_eq!(
+            dmm.update_paths(&[airlock_rule(), delete_trash]),
+            vec![2, 2]
+        );
+        assert_eq!(
+            dmm.dictionary()[&Key::new(0)],
+            vec![
+                Datum::new("/obj/machinery/door/new_airlock"),
+                Datum::new("/turf/open/floor"),
+            ]
+        );
+        // The migrated entry is now the same as the last one
+        assert_eq!(dmm.dictionary().len(), 2);
+        assert_eq!(
+            dmm.grid()[&(1, 1, 1)],
+            vec![Key::new(0), Key::new(0), Key::new(1), Key::new(0)]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Consistency checks of maps.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Key, DMM};
+
+/// A consistency problem. Problems of a dictionary entry are reported with the first tile using
+/// it, ordered by z, y then x.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Issue {
+    /// The grid uses a key that is not in the dictionary
+    UndefinedKey {
+        key: Key,
+        coords: (u32, u32, u32),
+    },
+    /// A dictionary entry is not used by the grid
+    UnusedKey(Key),
+    /// A tile inside the bounds of the map is not in the grid
+    MissingTile((u32, u32, u32)),
+    NoTurf {
+        key: Key,
+        coords: (u32, u32, u32),
+    },
+    NoArea {
+        key: Key,
+        coords: (u32, u32, u32),
+    },
+    SeveralTurfs {
+        key: Key,
+        coords: (u32, u32, u32),
+    },
+    SeveralAreas {
+        key: Key,
+        coords: (u32, u32, u32),
+    },
+}
+
+impl Issue {
+    /// Whether the map is unusable, rather than only untidy
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Issue::UnusedKey(_))
+    }
+
+    /// Tile where the issue is
+    pub fn coords(&self) -> Option<(u32, u32, u32)> {
+        match *self {
+            Issue::UnusedKey(_) => None,
+            Issue::MissingTile(coords)
+            | Issue::UndefinedKey { coords, .. }
+            | Issue::NoTurf { coords, .. }
+            | Issue::NoArea { coords, .. }
+            | Issue::SeveralTurfs { coords, .. }
+            | Issue::SeveralAreas { coords, .. } => Some(coords),
+        }
+    }
+}
+
+impl DMM {
+    /// Check the consistency of the map. Issues are ordered by location, issues without one last.
+    pub fn validate(&self) -> Vec<Issue> {
+        let tiles = self.tile_keys();
+
+        // First tile using each key
+        let mut first_uses: BTreeMap<Key, (u32, u32, u32)> = BTreeMap::new();
+        for (&(x, y, z), &key) in &tiles {
+            let first = first_uses.entry(key).or_insert((z, y, x));
+            *first = (*first).min((z, y, x));
+        }
+
+        let mut issues = Vec::new();
+        for (&key, &(z, y, x)) in &first_uses {
+            let coords = (x, y, z);
+            let datums = match self.dictionary.get(&key) {
+                Some(datums) => datums,
+                None => {
+                    issues.push(Issue::UndefinedKey { key, coords });
+                    continue;
+                }
+            };
+            match datums.iter().filter(|d| d.is_turf()).count() {
+                0 => issues.push(Issue::NoTurf { key, coords }),
+                1 => {}
+                _ => issues.push(Issue::SeveralTurfs { key, coords }),
+            }
+            match datums.iter().filter(|d| d.is_area()).count() {
+                0 => issues.push(Issue::NoArea { key, coords }),
+                1 => {}
+                _ => issues.push(Issue::SeveralAreas { key, coords }),
+            }
+        }
+
+        let (width, height, depth) = self.size();
+        for z in 1..=depth {
+            for y in 1..=height {
+                for x in 1..=width {
+                    if !tiles.contains_key(&(x, y, z)) {
+                        issues.push(Issue::MissingTile((x, y, z)));
+                    }
+                }
+            }
+        }
+        issues.sort_by_key(|issue| issue.coords().map(|(x, y, z)| (z, y, x)));
+
+        let used: BTreeSet<_> = first_uses.keys().collect();
+        let mut unused: Vec<_> = self
+            .dictionary
+            .keys()
+            .filter(|key| !used.contains(key))
+            .collect();
+        unused.sort_unstable();
+        issues.extend(unused.into_iter().map(|key| Issue::UnusedKey(*key)));
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Datum;
+
+    #[test]
+    fn test_validate() {
+        let floor = || Datum::new("/turf/open/floor");
+        let bridge = || Datum::new("/area/bridge");
+        let dmm = DMM::new(
+            vec![
+                (Key::new(0), vec![floor(), bridge()]),
+                (Key::new(1), vec![floor(), floor()]),
+                (Key::new(2), vec![bridge()]),
+            ]
+            .into_iter()
+            .collect(),
+            vec![
+                ((1, 1, 1), vec![Key::new(0), Key::new(1)]),
+                ((2, 2, 1), vec![Key::new(3)]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let issues = dmm.validate();
+        assert_eq!(
+            issues,
+            vec![
+                Issue::MissingTile((2, 1, 1)),
+                Issue::SeveralTurfs {
+                    key: Key::new(1),
+                    coords: (1, 2, 1)
+                },
+                Issue::NoArea {
+                    key: Key::new(1),
+                    coords: (1, 2, 1)
+                },
+                Issue::UndefinedKey {
+                    key: Key::new(3),
+                    coords: (2, 2, 1)
+                },
+                Issue::UnusedKey(Key::new(2)),
+            ]
+        );
+        assert!(!issues[4].is_error());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::DMM;
+
+/// Error returned by the z-level operations of a [`DMM`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ZLevelError {
+    /// Z-levels are numbered from 1, without gaps
+    InvalidZ(u32),
+    /// The new order does not name every existing z-level exactly once
+    NotAPermutation,
+}
+
+impl fmt::Display for ZLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZLevelError::InvalidZ(z) => write!(f, "invalid z-level {}", z),
+            ZLevelError::NotAPermutation => {
+                f.write_str("the new order must name every z-level exactly once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ZLevelError {}
+
+impl DMM {
+    /// Z-levels present in the grid, in ascending order
+    pub fn z_levels(&self) -> Vec<u32> {
+        self.grid
+            .keys()
+            .map(|&(_, _, z)| z)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Extract the z-level `z` as a single level map.
+    ///
+    /// The new map keeps the keys of the original one, and only the dictionary entries it uses.
+    pub fn z_level(&self, z: u32) -> Option<DMM> {
+        let grid: HashMap<_, _> = self
+            .grid
+            .iter()
+            .filter(|(coords, _)| coords.2 == z)
+            .map(|(&(x, y, _), keys)| ((x, y, 1), keys.clone()))
+            .collect();
+        if grid.is_empty() {
+            return None;
+        }
+
+        let mut level = DMM {
+            dictionary: self.dictionary.clone(),
+            grid,
+        };
+        level.prune_dictionary();
+        Some(level)
+    }
+
+    /// Split the map into one map per z-level, in ascending order
+    pub fn split_z_levels(&self) -> Vec<DMM> {
+        self.z_levels()
+            .into_iter()
+            .filter_map(|z| self.z_level(z))
+            .collect()
+    }
+
+    /// Stack several maps into one, the z-levels of each map following the previous ones
+    pub fn join_z_levels<I: IntoIterator<Item = DMM>>(levels: I) -> DMM {
+        let mut dmm = DMM::default();
+        for level in levels {
+            let z = dmm.z_levels().last().map_or(1, |z| z + 1);
+            dmm.insert_z_levels(z, &level).unwrap();
+        }
+        dmm
+    }
+
+    /// Insert the z-levels of `levels` at `z`, moving the existing z-levels from `z` upwards.
+    /// `z` is at most the z-level following the last one.
+    ///
+    /// Dictionary entries shared by both maps are kept, and the keys of `levels` are preserved
+    /// unless they are already used for something else.
+    pub fn insert_z_levels(&mut self, z: u32, levels: &DMM) -> Result<(), ZLevelError> {
+        let after_last = self.z_levels().last().map_or(1, |last| last + 1);
+        if z == 0 || z > after_last {
+            return Err(ZLevelError::InvalidZ(z));
+        }
+
+        let inserted = levels.z_levels();
+        let mapping = self.import_keys(levels, &levels.used_keys());
+        self.shift_z_levels(z, inserted.len() as i64);
+        for (new_z, old_z) in (z..).zip(inserted) {
+            for (&(x, y, _), keys) in levels.grid.iter().filter(|(c, _)| c.2 == old_z) {
+                self.grid
+                    .insert((x, y, new_z), keys.iter().map(|k| mapping[k]).collect());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the z-level `z` and return it, moving the z-levels above it down
+    pub fn remove_z_level(&mut self, z: u32) -> Option<DMM> {
+        let removed = self.z_level(z)?;
+        self.grid.retain(|coords, _| coords.2 != z);
+        self.shift_z_levels(z + 1, -1);
+        self.prune_dictionary();
+        Some(removed)
+    }
+
+    /// Reorder the z-levels. `order[i]` is the current z-level that becomes z-level `i + 1`.
+    pub fn reorder_z_levels(&mut self, order: &[u32]) -> Result<(), ZLevelError> {
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        if sorted != self.z_levels() {
+            return Err(ZLevelError::NotAPermutation);
+        }
+
+        let new_z: HashMap<u32, u32> = order.iter().copied().zip(1..).collect();
+        self.grid = self
+            .grid
+            .drain()
+            .map(|((x, y, z), keys)| ((x, y, new_z[&z]), keys))
+            .collect();
+        Ok(())
+    }
+
+    /// Move every z-level from `from` upwards by `offset`
+    fn shift_z_levels(&mut self, from: u32, offset: i64) {
+        self.grid = self
+            .grid
+            .drain()
+            .map(|((x, y, z), keys)| {
+                if z >= from {
+                    ((x, y, (i64::from(z) + offset) as u32), keys)
+                } else {
+                    ((x, y, z), keys)
+                }
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_map, Datum, Key};
+
+    fn level(turf: &str) -> DMM {
+        test_map(&[
+            ((1, 1, 1), [Datum::new("/turf/open/space")]),
+            ((1, 2, 1), [Datum::new(turf)]),
+        ])
+    }
+
+    #[test]
+    fn test_split_join() {
+        let joined = DMM::join_z_levels(vec![level("/turf/a"), level("/turf/b")]);
+
+        assert_eq!(joined.z_levels(), vec![1, 2]);
+        // Space is shared, the conflicting key 1 is moved
+        assert_eq!(joined.dictionary().len(), 3);
+        assert_eq!(joined.grid()[&(1, 1, 1)], vec![Key::new(0), Key::new(1)]);
+        assert_eq!(joined.grid()[&(1, 1, 2)], vec![Key::new(0), Key::new(2)]);
+
+        let split = joined.split_z_levels();
+        assert_eq!(split[0], level("/turf/a"));
+        assert_eq!(
+            split[1].iter().collect::<HashMap<_, _>>(),
+            level("/turf/b").iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_insert_remove_reorder() {
+        let mut dmm = DMM::join_z_levels(vec![level("/turf/a"), level("/turf/c")]);
+        dmm.insert_z_levels(2, &level("/turf/b")).unwrap();
+        assert_eq!(dmm.z_levels(), vec![1, 2, 3]);
+        assert_eq!(
+            dmm.insert_z_levels(5, &level("/turf/d")),
+            Err(ZLevelError::InvalidZ(5))
+        );
+        assert_eq!(
+            dmm.insert_z_levels(0, &level("/turf/d")),
+            Err(ZLevelError::InvalidZ(0))
+        );
+        assert_eq!(dmm.z_levels(), vec![1, 2, 3]);
+        assert_eq!(
+            dmm.iter().find(|c| c.0 == (1, 2, 3)).unwrap().1[0].path(),
+            "/turf/c"
+        );
+
+        dmm.reorder_z_levels(&[3, 1, 2]).unwrap();
+        assert_eq!(
+            dmm.iter().find(|c| c.0 == (1, 2, 1)).unwrap().1[0].path(),
+            "/turf/c"
+        );
+        assert_eq!(
+            dmm.reorder_z_levels(&[1, 1, 2]),
+            Err(ZLevelError::NotAPermutation)
+        );
+
+        let removed = dmm.remove_z_level(1).unwrap();
+        assert_eq!(removed.iter().next().unwrap().1.len(), 1);
+        assert_eq!(dmm.z_levels(), vec![1, 2]);
+        assert_eq!(dmm.dictionary().len(), 3);
+        assert!(dmm.remove_z_level(3).is_none());
+    }
+}